@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::symbol;
+use crate::graph;
+use crate::query;
+use crate::range;
+
+pub enum Aggregate {
+    Count,
+    CountDistinct(String),
+    Min(String, range::Encoding),
+    Max(String, range::Encoding)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Value {
+    Count(usize),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Missing
+}
+
+pub struct AggregateRow {
+    pub key: Vec<Option<symbol::Symbol>>,
+    pub values: Vec<Value>
+}
+
+fn decode_value(encoding: range::Encoding, bits: u64, data_length: usize) -> Value {
+    match encoding {
+        range::Encoding::Unsigned => Value::Unsigned(bits),
+        range::Encoding::Signed => {
+            let shift = 64-data_length as u32;
+            Value::Signed(((bits<<shift) as i64)>>shift)
+        },
+        range::Encoding::Float => match data_length {
+            32 => Value::Float(f32::from_bits(bits as u32) as f64),
+            _ => Value::Float(f64::from_bits(bits))
+        }
+    }
+}
+
+enum Accumulator {
+    Count(usize),
+    CountDistinct(Option<usize>, HashSet<symbol::Symbol>),
+    Extreme(Option<usize>, range::Encoding, bool, Option<(u64, u64, usize)>)
+}
+
+impl Accumulator {
+    fn new(aggregate: &Aggregate, bindings: &query::Bindings) -> Self {
+        match aggregate {
+            Aggregate::Count => Accumulator::Count(0),
+            Aggregate::CountDistinct(variable) => Accumulator::CountDistinct(bindings.column(variable), HashSet::new()),
+            Aggregate::Min(variable, encoding) => Accumulator::Extreme(bindings.column(variable), *encoding, false, None),
+            Aggregate::Max(variable, encoding) => Accumulator::Extreme(bindings.column(variable), *encoding, true, None)
+        }
+    }
+
+    fn add(&mut self, row: &[Option<symbol::Symbol>]) {
+        match self {
+            Accumulator::Count(count) => { *count += 1; },
+            Accumulator::CountDistinct(column, symbols) => {
+                if let Some(symbol) = column.and_then(|column| row[column]) {
+                    symbols.insert(symbol);
+                }
+            },
+            Accumulator::Extreme(column, encoding, maximum, extreme) => {
+                let (bits, data_length) = match column.and_then(|column| row[column]).and_then(graph::read_leading_bits) {
+                    Some(leading_bits) => leading_bits,
+                    None => { return; }
+                };
+                if let Some(key) = range::encode_key(*encoding, bits, data_length) {
+                    if extreme.is_none_or(|(extreme_key, _bits, _data_length)| if *maximum { key > extreme_key } else { key < extreme_key }) {
+                        *extreme = Some((key, bits, data_length));
+                    }
+                }
+            }
+        }
+    }
+
+    fn value(&self) -> Value {
+        match self {
+            Accumulator::Count(count) => Value::Count(*count),
+            Accumulator::CountDistinct(_column, symbols) => Value::Count(symbols.len()),
+            Accumulator::Extreme(_column, encoding, _maximum, extreme) => match extreme {
+                Some((_key, bits, data_length)) => decode_value(*encoding, *bits, *data_length),
+                None => Value::Missing
+            }
+        }
+    }
+}
+
+fn count_pattern(pattern: &query::Pattern, group_by: &[String], aggregate_count: usize) -> Option<Vec<AggregateRow>> {
+    let mut variables: Vec<&String> = vec![];
+    let mut triple = [symbol::Symbol(0, 0); 3];
+    let mut grouping_masks = [graph::QueryMask::Ignore; 3];
+    let mut counting_masks = [graph::QueryMask::Varying; 3];
+    for triple_index in 0..3 {
+        match &pattern[triple_index] {
+            query::Term::Constant(symbol) => {
+                triple[triple_index] = *symbol;
+                grouping_masks[triple_index] = graph::QueryMask::Match;
+                counting_masks[triple_index] = graph::QueryMask::Match;
+            },
+            query::Term::Variable(name) => {
+                if variables.contains(&name) {
+                    return None;
+                }
+                variables.push(name);
+                if group_by.contains(name) {
+                    grouping_masks[triple_index] = graph::QueryMask::Varying;
+                    counting_masks[triple_index] = graph::QueryMask::Match;
+                }
+            }
+        }
+    }
+    let mut positions: Vec<usize> = vec![];
+    for name in group_by {
+        positions.push((0..3).find(|triple_index| pattern[*triple_index] == query::Term::Variable(name.clone()))?);
+    }
+    if group_by.is_empty() {
+        return Some(vec![AggregateRow{key: vec![], values: vec![Value::Count(graph::count_triples(graph::query_mask(counting_masks), triple)); aggregate_count]}]);
+    }
+    Some(graph::query_triples(graph::query_mask(grouping_masks), triple).into_iter().map(|key_triple| AggregateRow{
+        key: positions.iter().map(|triple_index| Some(key_triple[*triple_index])).collect(),
+        values: vec![Value::Count(graph::count_triples(graph::query_mask(counting_masks), key_triple)); aggregate_count]
+    }).collect())
+}
+
+
+
+pub fn aggregate(elements: &[query::Element], group_by: &[String], aggregates: &[Aggregate]) -> Vec<AggregateRow> {
+    let mut result = match elements {
+        [query::Element::Pattern(pattern)] if aggregates.iter().all(|aggregate| matches!(aggregate, Aggregate::Count)) => count_pattern(pattern, group_by, aggregates.len()),
+        _ => None
+    }.unwrap_or_else(|| {
+        let bindings = query::Query::from_elements(elements).evaluate();
+        let columns: Vec<Option<usize>> = group_by.iter().map(|variable| bindings.column(variable)).collect();
+        let mut groups: HashMap<Vec<Option<symbol::Symbol>>, Vec<Accumulator>> = HashMap::new();
+        if group_by.is_empty() {
+            groups.insert(vec![], aggregates.iter().map(|aggregate| Accumulator::new(aggregate, &bindings)).collect());
+        }
+        for row in &bindings.rows {
+            let key: Vec<Option<symbol::Symbol>> = columns.iter().map(|column| column.and_then(|column| row[column])).collect();
+            let accumulators = groups.entry(key).or_insert_with(|| aggregates.iter().map(|aggregate| Accumulator::new(aggregate, &bindings)).collect());
+            for accumulator in accumulators.iter_mut() {
+                accumulator.add(row);
+            }
+        }
+        groups.into_iter().map(|(key, accumulators)| AggregateRow{
+            key,
+            values: accumulators.iter().map(|accumulator| accumulator.value()).collect()
+        }).collect()
+    });
+    if graph::ordered_results() {
+        result.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+    }
+    result
+}
@@ -15,6 +15,7 @@ pub struct BitwiseRead<'a> {
 }
 
 impl BitwiseRead<'_> {
+    #[allow(clippy::redundant_field_names)]
     pub fn new<'a>(src: &'a [usize], length: usize, offset: usize) -> BitwiseRead<'a> {
         let result: BitwiseRead<'a> = BitwiseRead {
             src: src,
@@ -54,6 +55,7 @@ pub struct BitwiseWrite<'a> {
 }
 
 impl BitwiseWrite<'_> {
+    #[allow(clippy::redundant_field_names)]
     pub fn new<'a>(dst: &'a mut [usize], length: usize, offset: usize) -> BitwiseWrite<'a> {
         let result: BitwiseWrite<'a> = BitwiseWrite {
             dst: dst,
@@ -64,10 +66,6 @@ impl BitwiseWrite<'_> {
         result
     }
 
-    pub fn more(&self) -> bool {
-        self.length > 0
-    }
-
     pub fn next(&mut self, mut src: usize) {
         let mask = lsb_bitmask(self.length as usize);
         src &= mask;
@@ -82,6 +80,7 @@ impl BitwiseWrite<'_> {
     }
 }
 
+#[allow(clippy::manual_div_ceil, clippy::ptr_offset_with_cast)]
 pub fn bitwise_copy_nonoverlapping(dst: &mut[usize], src: &[usize], dst_offset: usize, src_offset: usize, length: usize) {
     if length == 0 {
         return;
@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::bitops;
+use crate::symbol;
+use crate::graph;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub fn hash_content<I: Iterator<Item=usize>>(words: I, data_length: usize) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut feed = |word: u64| {
+        for byte in word.to_le_bytes().iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    feed(data_length as u64);
+    for word in words {
+        feed(word as u64);
+    }
+    hash
+}
+
+struct ContentIndex {
+    buckets: HashMap<u64, HashSet<symbol::Symbol>>,
+    hashes: HashMap<symbol::Symbol, u64>,
+    stale: HashSet<symbol::Symbol>
+}
+
+impl ContentIndex {
+    fn remove(&mut self, symbol: symbol::Symbol) {
+        if let Some(hash) = self.hashes.remove(&symbol) {
+            let bucket = self.buckets.get_mut(&hash).unwrap();
+            bucket.remove(&symbol);
+            if bucket.is_empty() {
+                self.buckets.remove(&hash);
+            }
+        }
+    }
+
+    fn insert(&mut self, symbol: symbol::Symbol, hash: u64) {
+        self.hashes.insert(symbol, hash);
+        self.buckets.entry(hash).or_default().insert(symbol);
+    }
+}
+
+thread_local!(static CONTENT_INDEX: RefCell<Option<ContentIndex>> = const { RefCell::new(None) });
+
+
+
+pub fn is_enabled() -> bool {
+    CONTENT_INDEX.with(|content_index_cell| content_index_cell.borrow().is_some())
+}
+
+pub fn enable_content_index() {
+    if is_enabled() {
+        return;
+    }
+    let mut content_index = ContentIndex{buckets: HashMap::new(), hashes: HashMap::new(), stale: HashSet::new()};
+    for (symbol, hash) in graph::content_hashes() {
+        content_index.insert(symbol, hash);
+    }
+    CONTENT_INDEX.with(|content_index_cell| {
+        content_index_cell.replace(Some(content_index));
+    })
+}
+
+pub fn disable_content_index() {
+    CONTENT_INDEX.with(|content_index_cell| {
+        content_index_cell.replace(None);
+    })
+}
+
+pub fn content_changed(symbol: symbol::Symbol) {
+    CONTENT_INDEX.with(|content_index_cell| {
+        if let Some(content_index) = content_index_cell.borrow_mut().as_mut() {
+            content_index.stale.insert(symbol);
+        }
+    })
+}
+
+fn refresh_stale() {
+    let stale = CONTENT_INDEX.with(|content_index_cell| {
+        content_index_cell.borrow_mut().as_mut().map_or(HashSet::new(), |content_index| std::mem::take(&mut content_index.stale))
+    });
+    let hashes: Vec<(symbol::Symbol, Option<u64>)> = stale.into_iter().map(|symbol| (symbol, graph::content_hash(symbol))).collect();
+    CONTENT_INDEX.with(|content_index_cell| {
+        if let Some(content_index) = content_index_cell.borrow_mut().as_mut() {
+            for (symbol, hash) in hashes {
+                content_index.remove(symbol);
+                if let Some(hash) = hash {
+                    content_index.insert(symbol, hash);
+                }
+            }
+        }
+    })
+}
+
+pub fn find_symbols_by_content(data_content: &[usize], data_length: usize) -> Option<Vec<symbol::Symbol>> {
+    refresh_stale();
+    let candidates: Vec<symbol::Symbol> = CONTENT_INDEX.with(|content_index_cell| {
+        let content_index = content_index_cell.borrow();
+        let content_index = content_index.as_ref()?;
+        Some(match content_index.buckets.get(&hash_content(bitops::BitwiseRead::new(data_content, data_length, 0), data_length)) {
+            Some(bucket) => bucket.iter().cloned().collect(),
+            None => vec![]
+        })
+    })?;
+    let expected: Vec<usize> = bitops::BitwiseRead::new(data_content, data_length, 0).collect();
+    let mut actual: Vec<usize> = vec![0; expected.len()];
+    let mut result: Vec<symbol::Symbol> = candidates.into_iter().filter(|symbol| {
+        graph::get_length(*symbol) == data_length && graph::read_data(*symbol, 0, data_length, &mut actual) && actual == expected
+    }).collect();
+    if graph::ordered_results() {
+        result.sort_unstable();
+    }
+    Some(result)
+}
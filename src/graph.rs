@@ -1,8 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::cell::Cell;
 use std::cell::RefCell;
 use crate::bitops;
 use crate::symbol;
+use crate::history;
+use crate::content;
+use crate::random;
+use crate::range;
+use crate::rope;
+use crate::rules;
+use crate::text;
+use crate::views;
 
 pub type Triple = [symbol::Symbol; 3];
 
@@ -11,7 +22,7 @@ type BetaCollection = HashMap<symbol::Symbol, GammaCollection>;
 type AlphaCollection = HashMap<symbol::Identity, SymbolHandle>;
 
 struct SymbolHandle {
-    data_content: RefCell<Box<[usize]>>,
+    data_content: RefCell<rope::BitRope>,
     data_length: usize,
     subindices: [BetaCollection; 6]
 }
@@ -23,12 +34,62 @@ struct NamespaceHandle {
 
 type NamespaceIndex = HashMap<symbol::Identity, NamespaceHandle>;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, PartialEq)]
+pub struct ContinuationToken {
+    generation: usize,
+    key: Vec<usize>
+}
+
+impl ContinuationToken {
+    pub fn new() -> Self {
+        Self{generation: generation(), key: vec![]}
+    }
+
+    pub fn encode(&self) -> String {
+        let mut result = format!("{:x}", self.generation);
+        for part in &self.key {
+            result += &format!(".{:x}", part);
+        }
+        result
+    }
+
+    pub fn decode(encoded: &str) -> Option<Self> {
+        let mut parts = encoded.split('.');
+        let generation = usize::from_str_radix(parts.next()?, 16).ok()?;
+        let key = parts.map(|part| usize::from_str_radix(part, 16).ok()).collect::<Option<Vec<usize>>>()?;
+        Some(Self{generation, key})
+    }
+
+    fn paginate<F: FnOnce(&[usize]) -> Option<(Vec<T>, Option<Vec<usize>>)>, T>(&self, f: F) -> Option<(Vec<T>, Option<Self>)> {
+        if self.generation != generation() {
+            return None;
+        }
+        let (page, key) = f(&self.key)?;
+        Some((page, key.map(|key| Self{generation: self.generation, key})))
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug)]
 pub enum TripleIndex {
     EAV, AVE, VEA,
     EVA, AEV, VAE
 }
 
+const TRIPLE_INDICES: [TripleIndex; 6] = [
+    TripleIndex::EAV, TripleIndex::AVE, TripleIndex::VEA,
+    TripleIndex::EVA, TripleIndex::AEV, TripleIndex::VAE
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum QueryMask {
+    Match, Varying, Ignore
+}
+
+pub fn query_mask(masks: [QueryMask; 3]) -> usize {
+    masks[0] as usize+(masks[1] as usize)*3+(masks[2] as usize)*9
+}
+
 #[derive(Clone, Copy)]
 enum TripleQueryFunc {
     SearchMMM,
@@ -82,9 +143,32 @@ const TRIPLE_NORMALIZED: TriplePermutation = [
 const META_NAMESPACE_IDENTITY: usize = 0;
 
 thread_local!(static NAMESPACE_INDEX: RefCell<NamespaceIndex> = RefCell::new(HashMap::new()));
+thread_local!(static TRIPLE_COUNT: Cell<usize> = const { Cell::new(0) });
+thread_local!(static GENERATION: Cell<usize> = const { Cell::new(0) });
+thread_local!(static BETA_SIZES: RefCell<[Vec<usize>; 6]> = const { RefCell::new([const { Vec::new() }; 6]) });
+thread_local!(static GAMMA_SIZES: RefCell<[Vec<usize>; 6]> = const { RefCell::new([const { Vec::new() }; 6]) });
+
+fn advance_generation() {
+    GENERATION.with(|generation| generation.set(generation.get().wrapping_add(1)));
+}
+
+pub fn generation() -> usize {
+    GENERATION.with(|generation| generation.get())
+}
+
+thread_local!(static ORDERED_RESULTS: Cell<bool> = const { Cell::new(false) });
+
+pub fn set_ordered_results(ordered: bool) {
+    ORDERED_RESULTS.with(|ordered_results| ordered_results.set(ordered));
+}
+
+pub fn ordered_results() -> bool {
+    ORDERED_RESULTS.with(|ordered_results| ordered_results.get())
+}
 
 
 
+#[allow(clippy::map_entry)]
 fn manifest_namespace(namespace_index: &mut NamespaceIndex, namespace_identity: symbol::Identity) {
     if !namespace_index.contains_key(&namespace_identity) {
         let namespace_handle = NamespaceHandle{free_pool: symbol::IdentityPool::new(), symbol_index: AlphaCollection::new()};
@@ -106,30 +190,41 @@ fn get_symbol_handle(namespace_index: &NamespaceIndex, symbol: symbol::Symbol) -
     }
 }
 
+#[allow(clippy::needless_return)]
 fn manifest_symbol_internal(namespace_index: &mut NamespaceIndex, symbol: symbol::Symbol) -> bool {
     if symbol == symbol::Symbol(META_NAMESPACE_IDENTITY, META_NAMESPACE_IDENTITY) {
         manifest_namespace(namespace_index, META_NAMESPACE_IDENTITY);
     }
-    let namespace_handle = namespace_index.get_mut(&symbol.0).unwrap();
+    let namespace_handle = match namespace_index.get_mut(&symbol.0) {
+        Some(namespace_handle) => namespace_handle,
+        None => { return false; }
+    };
     if namespace_handle.symbol_index.contains_key(&symbol.1) {
         return false;
     }
-    let symbol_handle = SymbolHandle{data_content: RefCell::new(Box::new([])), data_length: 0, subindices: [BetaCollection::new(), BetaCollection::new(), BetaCollection::new(), BetaCollection::new(), BetaCollection::new(), BetaCollection::new()]};
+    let symbol_handle = SymbolHandle{data_content: RefCell::new(rope::BitRope::new()), data_length: 0, subindices: [BetaCollection::new(), BetaCollection::new(), BetaCollection::new(), BetaCollection::new(), BetaCollection::new(), BetaCollection::new()]};
     assert!(namespace_handle.symbol_index.insert(symbol.1, symbol_handle).is_none());
     assert!(namespace_handle.free_pool.remove(symbol.1));
+    update_data_indices(symbol, namespace_handle.symbol_index.get(&symbol.1).unwrap());
     if symbol.0 == META_NAMESPACE_IDENTITY {
         manifest_namespace(namespace_index, symbol.1);
     }
+    advance_generation();
     return true;
 }
 
 pub fn manifest_symbol(symbol: symbol::Symbol) -> bool {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let mut namespace_index = namespace_index_cell.borrow_mut();
-        manifest_symbol_internal(&mut namespace_index, symbol)
+        let result = manifest_symbol_internal(&mut namespace_index, symbol);
+        if result {
+            history::record(history::Operation::ManifestSymbol(symbol));
+        }
+        result
     })
 }
 
+#[allow(clippy::init_numbered_fields)]
 pub fn create_symbol(namespace_identity: symbol::Identity) -> symbol::Symbol {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let mut namespace_index = namespace_index_cell.borrow_mut();
@@ -137,52 +232,72 @@ pub fn create_symbol(namespace_identity: symbol::Identity) -> symbol::Symbol {
         let symbol_identity: symbol::Identity = namespace_handle.free_pool.get();
         let symbol = symbol::Symbol{0: namespace_identity, 1: symbol_identity};
         manifest_symbol_internal(&mut namespace_index, symbol);
+        history::record(history::Operation::ManifestSymbol(symbol));
         symbol
     })
 }
 
-pub fn release_symbol(symbol: symbol::Symbol) -> bool {
-    NAMESPACE_INDEX.with(|namespace_index_cell| {
-        let mut namespace_index = namespace_index_cell.borrow_mut();
-        if symbol.0 == META_NAMESPACE_IDENTITY {
-            match namespace_index.get_mut(&symbol.1) {
-                Some(namespace_handle) => {
-                    if !namespace_handle.free_pool.is_full() {
+fn release_symbol_internal(namespace_index: &mut NamespaceIndex, symbol: symbol::Symbol) -> bool {
+    if symbol.0 == META_NAMESPACE_IDENTITY {
+        match namespace_index.get_mut(&symbol.1) {
+            Some(namespace_handle) => {
+                if !namespace_handle.free_pool.is_full() {
+                    return false;
+                }
+            },
+            None => { return false; }
+        }
+    }
+    match namespace_index.get_mut(&symbol.0) {
+        Some(namespace_handle) => {
+            match namespace_handle.symbol_index.get(&symbol.1) {
+                Some(symbol_handle) => {
+                    if symbol_handle.data_length > 0 {
                         return false;
                     }
+                    for subindex in &symbol_handle.subindices {
+                        if !subindex.is_empty() {
+                            return false;
+                        }
+                    }
                 },
                 None => { return false; }
             }
+            assert!(namespace_handle.symbol_index.remove(&symbol.1).is_some());
+            assert!(namespace_handle.free_pool.insert(symbol.1));
+        },
+        None => { return false; }
+    };
+    if symbol.0 == META_NAMESPACE_IDENTITY {
+        assert!(namespace_index.remove(&symbol.1).is_some());
+    }
+    content::content_changed(symbol);
+    range::data_changed(symbol, None);
+    text::data_changed(symbol);
+    advance_generation();
+    true
+}
+
+pub fn release_symbol(symbol: symbol::Symbol) -> bool {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let mut namespace_index = namespace_index_cell.borrow_mut();
+        let result = release_symbol_internal(&mut namespace_index, symbol);
+        if result {
+            history::record(history::Operation::ReleaseSymbol(symbol));
         }
-        match namespace_index.get_mut(&symbol.0) {
-            Some(namespace_handle) => {
-                match namespace_handle.symbol_index.get(&symbol.1) {
-                    Some(symbol_handle) => {
-                        if symbol_handle.data_length > 0 {
-                            return false;
-                        }
-                        for subindex in &symbol_handle.subindices {
-                            if subindex.len() > 0 {
-                                return false;
-                            }
-                        }
-                    },
-                    None => { return false; }
-                }
-                assert!(namespace_handle.symbol_index.remove(&symbol.1).is_some());
-                assert!(namespace_handle.free_pool.insert(symbol.1));
-            },
-            None => { return false; }
-        };
-        if symbol.0 == META_NAMESPACE_IDENTITY {
-            assert!(namespace_index.remove(&symbol.1).is_some());
-        }
-        true
+        result
     })
 }
 
 
 
+pub fn is_manifested(symbol: symbol::Symbol) -> bool {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        get_symbol_handle(&namespace_index, symbol).is_some()
+    })
+}
+
 pub fn get_length(symbol: symbol::Symbol) -> usize {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let namespace_index = namespace_index_cell.borrow();
@@ -193,38 +308,22 @@ pub fn get_length(symbol: symbol::Symbol) -> usize {
     })
 }
 
-pub fn crease_length(symbol: symbol::Symbol, offset: usize, length: isize) -> bool {
+pub fn get_capacity(symbol: symbol::Symbol) -> usize {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        match get_symbol_handle(&namespace_index, symbol) {
+            Some(symbol_handle) => symbol_handle.data_content.borrow().capacity(),
+            None => 0
+        }
+    })
+}
+
+pub fn reserve_capacity(symbol: symbol::Symbol, additional: usize) -> bool {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let mut namespace_index = namespace_index_cell.borrow_mut();
         match get_symbol_handle_mut(&mut namespace_index, symbol) {
             Some(symbol_handle) => {
-                let length_abs: usize;
-                if length < 0 {
-                    length_abs = -length as usize;
-                    if offset+length_abs > symbol_handle.data_length {
-                        return false;
-                    }
-                } else {
-                    length_abs = length as usize;
-                    if offset > symbol_handle.data_length {
-                        return false;
-                    }
-                }
-                let new_data_length = ((symbol_handle.data_length as isize)+length) as usize;
-                let mut new_data_content: Box<[usize]> = vec![0; (new_data_length+bitops::ARCHITECTURE_SIZE-1)/bitops::ARCHITECTURE_SIZE].into_boxed_slice();
-                for i in 0..(offset+bitops::ARCHITECTURE_SIZE-1)/bitops::ARCHITECTURE_SIZE {
-                    new_data_content[i] = symbol_handle.data_content.borrow()[i];
-                }
-                if offset%bitops::ARCHITECTURE_SIZE > 0 {
-                    new_data_content[offset/bitops::ARCHITECTURE_SIZE] &= bitops::lsb_bitmask(offset%bitops::ARCHITECTURE_SIZE);
-                }
-                if length < 0 {
-                    bitops::bitwise_copy_nonoverlapping(&mut new_data_content, &symbol_handle.data_content.borrow(), offset, offset+length_abs, symbol_handle.data_length-offset-length_abs);
-                } else {
-                    bitops::bitwise_copy_nonoverlapping(&mut new_data_content, &symbol_handle.data_content.borrow(), offset+length_abs, offset, symbol_handle.data_length-offset);
-                }
-                symbol_handle.data_length = new_data_length;
-                symbol_handle.data_content.replace(new_data_content);
+                symbol_handle.data_content.get_mut().reserve(additional);
                 true
             },
             None => false
@@ -232,21 +331,12 @@ pub fn crease_length(symbol: symbol::Symbol, offset: usize, length: isize) -> bo
     })
 }
 
-pub fn read_data(symbol: symbol::Symbol, offset: usize, length: usize, dst: &mut [usize]) -> bool {
+pub fn shrink_capacity_to_fit(symbol: symbol::Symbol) -> bool {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
-        let namespace_index = namespace_index_cell.borrow();
-        match get_symbol_handle(&namespace_index, symbol) {
+        let mut namespace_index = namespace_index_cell.borrow_mut();
+        match get_symbol_handle_mut(&mut namespace_index, symbol) {
             Some(symbol_handle) => {
-                if offset+length > symbol_handle.data_length {
-                    return false;
-                }
-                let data_content = symbol_handle.data_content.borrow();
-                let bitwise_read = bitops::BitwiseRead::new(&data_content, length, offset);
-                let mut index: usize = 0;
-                for src in bitwise_read {
-                    dst[index] = src;
-                    index += 1;
-                }
+                symbol_handle.data_content.get_mut().shrink_to_fit();
                 true
             },
             None => false
@@ -254,21 +344,148 @@ pub fn read_data(symbol: symbol::Symbol, offset: usize, length: usize, dst: &mut
     })
 }
 
-pub fn write_data(symbol: symbol::Symbol, offset: usize, length: usize, src: &[usize]) -> bool {
+fn leading_bits(symbol_handle: &SymbolHandle) -> u64 {
+    let mut bits: u64 = 0;
+    for (index, word) in symbol_handle.data_content.borrow().read(0, symbol_handle.data_length.min(64)).iter().enumerate() {
+        bits |= (*word as u64)<<(index*bitops::ARCHITECTURE_SIZE);
+    }
+    bits
+}
+
+pub fn read_leading_bits(symbol: symbol::Symbol) -> Option<(u64, usize)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        get_symbol_handle(&namespace_index, symbol).map(|symbol_handle| (leading_bits(symbol_handle), symbol_handle.data_length))
+    })
+}
+
+fn data_bytes(symbol_handle: &SymbolHandle) -> Vec<u8> {
+    symbol_handle.data_content.borrow().words().flat_map(|word| word.to_le_bytes()).take(symbol_handle.data_length/8).collect()
+}
+
+fn data_hash(symbol_handle: &SymbolHandle) -> u64 {
+    content::hash_content(symbol_handle.data_content.borrow().words(), symbol_handle.data_length)
+}
+
+fn update_data_indices(symbol: symbol::Symbol, symbol_handle: &SymbolHandle) {
+    content::content_changed(symbol);
+    if range::is_indexed(symbol.0) {
+        range::data_changed(symbol, Some((leading_bits(symbol_handle), symbol_handle.data_length)));
+    }
+    text::data_changed(symbol);
+}
+
+pub fn read_bytes(symbol: symbol::Symbol) -> Option<Vec<u8>> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        get_symbol_handle(&namespace_index, symbol).map(data_bytes)
+    })
+}
+
+pub fn bytes_in_namespace(namespace_identity: symbol::Identity) -> Vec<(symbol::Symbol, Vec<u8>)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut result: Vec<(symbol::Symbol, Vec<u8>)> = vec![];
+        if let Some(namespace_handle) = namespace_index.get(&namespace_identity) {
+            for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
+                result.push((symbol::Symbol(namespace_identity, *symbol_identity), data_bytes(symbol_handle)));
+            }
+        }
+        result
+    })
+}
+
+pub fn leading_bits_in_namespace(namespace_identity: symbol::Identity) -> Vec<(symbol::Symbol, u64, usize)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut result: Vec<(symbol::Symbol, u64, usize)> = vec![];
+        if let Some(namespace_handle) = namespace_index.get(&namespace_identity) {
+            for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
+                result.push((symbol::Symbol(namespace_identity, *symbol_identity), leading_bits(symbol_handle), symbol_handle.data_length));
+            }
+        }
+        result
+    })
+}
+
+pub fn content_hash(symbol: symbol::Symbol) -> Option<u64> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        get_symbol_handle(&namespace_index, symbol).map(data_hash)
+    })
+}
+
+pub fn content_hashes() -> Vec<(symbol::Symbol, u64)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut result: Vec<(symbol::Symbol, u64)> = vec![];
+        for (namespace_identity, namespace_handle) in namespace_index.iter() {
+            for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
+                result.push((symbol::Symbol(*namespace_identity, *symbol_identity), data_hash(symbol_handle)));
+            }
+        }
+        result
+    })
+}
+
+fn read_bits(symbol_handle: &SymbolHandle, offset: usize, length: usize) -> Box<[usize]> {
+    symbol_handle.data_content.borrow().read(offset, length).into_boxed_slice()
+}
+
+fn crease_length_internal(namespace_index: &mut NamespaceIndex, symbol: symbol::Symbol, offset: usize, length: isize) -> bool {
+    match get_symbol_handle_mut(namespace_index, symbol) {
+        Some(symbol_handle) => {
+            if length < 0 {
+                if offset+(-length as usize) > symbol_handle.data_length {
+                    return false;
+                }
+            } else if offset > symbol_handle.data_length {
+                return false;
+            }
+            if length < 0 {
+                symbol_handle.data_content.get_mut().remove(offset, -length as usize);
+            } else {
+                symbol_handle.data_content.get_mut().insert_zeros(offset, length as usize);
+            }
+            symbol_handle.data_length = ((symbol_handle.data_length as isize)+length) as usize;
+            update_data_indices(symbol, symbol_handle);
+            true
+        },
+        None => false
+    }
+}
+
+pub fn crease_length(symbol: symbol::Symbol, offset: usize, length: isize) -> bool {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let mut namespace_index = namespace_index_cell.borrow_mut();
-        match get_symbol_handle_mut(&mut namespace_index, symbol) {
+        let removed_data = if length < 0 && history::is_enabled() {
+            match get_symbol_handle(&namespace_index, symbol) {
+                Some(symbol_handle) if offset+(-length as usize) <= symbol_handle.data_length => read_bits(symbol_handle, offset, -length as usize),
+                _ => { return false; }
+            }
+        } else { Box::new([]) };
+        if !crease_length_internal(&mut namespace_index, symbol, offset, length) {
+            return false;
+        }
+        if length < 0 {
+            history::record(history::Operation::DecreaseLength(symbol, offset, -length as usize, removed_data));
+        } else {
+            history::record(history::Operation::IncreaseLength(symbol, offset, length as usize));
+        }
+        true
+    })
+}
+
+pub fn read_data(symbol: symbol::Symbol, offset: usize, length: usize, dst: &mut [usize]) -> bool {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        match get_symbol_handle(&namespace_index, symbol) {
             Some(symbol_handle) => {
                 if offset+length > symbol_handle.data_length {
                     return false;
                 }
-                let mut data_content = symbol_handle.data_content.borrow_mut();
-                let mut bitwise_write = bitops::BitwiseWrite::new(&mut data_content, length, offset);
-                let mut index: usize = 0;
-                while bitwise_write.more() {
-                    bitwise_write.next(src[index]);
-                    index += 1;
-                }
+                let data_content = read_bits(symbol_handle, offset, length);
+                dst[..data_content.len()].copy_from_slice(&data_content);
                 true
             },
             None => false
@@ -276,6 +493,40 @@ pub fn write_data(symbol: symbol::Symbol, offset: usize, length: usize, src: &[u
     })
 }
 
+fn write_data_internal(namespace_index: &mut NamespaceIndex, symbol: symbol::Symbol, offset: usize, length: usize, src: &[usize]) -> bool {
+    match get_symbol_handle_mut(namespace_index, symbol) {
+        Some(symbol_handle) => {
+            if offset+length > symbol_handle.data_length {
+                return false;
+            }
+            symbol_handle.data_content.get_mut().write(offset, length, src);
+            update_data_indices(symbol, symbol_handle);
+            true
+        },
+        None => false
+    }
+}
+
+pub fn write_data(symbol: symbol::Symbol, offset: usize, length: usize, src: &[usize]) -> bool {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let mut namespace_index = namespace_index_cell.borrow_mut();
+        let previous_data = if history::is_enabled() {
+            match get_symbol_handle(&namespace_index, symbol) {
+                Some(symbol_handle) if offset+length <= symbol_handle.data_length => read_bits(symbol_handle, offset, length),
+                _ => { return false; }
+            }
+        } else { Box::new([]) };
+        if !write_data_internal(&mut namespace_index, symbol, offset, length, src) {
+            return false;
+        }
+        if history::is_enabled() {
+            let written_data = read_bits(get_symbol_handle(&namespace_index, symbol).unwrap(), offset, length);
+            history::record(history::Operation::WriteData(symbol, offset, length, previous_data, written_data));
+        }
+        true
+    })
+}
+
 pub fn replace_data(dst_symbol: symbol::Symbol, dst_offset: usize, src_symbol: symbol::Symbol, src_offset: usize, length: usize) -> bool {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let namespace_index = namespace_index_cell.borrow();
@@ -290,22 +541,112 @@ pub fn replace_data(dst_symbol: symbol::Symbol, dst_offset: usize, src_symbol: s
         if dst_offset+length > dst_symbol_handle.data_length || src_offset+length > src_symbol_handle.data_length {
             return false;
         }
-        bitops::bitwise_copy_nonoverlapping(&mut dst_symbol_handle.data_content.borrow_mut(), &src_symbol_handle.data_content.borrow(), dst_offset, src_offset, length);
+        let previous_data = if history::is_enabled() { read_bits(dst_symbol_handle, dst_offset, length) } else { Box::new([]) };
+        let data_content = read_bits(src_symbol_handle, src_offset, length);
+        dst_symbol_handle.data_content.borrow_mut().write(dst_offset, length, &data_content);
+        update_data_indices(dst_symbol, dst_symbol_handle);
+        if history::is_enabled() {
+            history::record(history::Operation::WriteData(dst_symbol, dst_offset, length, previous_data, read_bits(dst_symbol_handle, dst_offset, length)));
+        }
         true
     })
 }
 
 
+const SEARCH_WINDOW_LENGTH: usize = 1<<16;
+
+fn find_in_window(data_content: &[usize], data_length: usize, pattern: &[usize], pattern_length: usize, start_offset: usize, byte_aligned: bool) -> Option<usize> {
+    if byte_aligned && pattern_length%8 == 0 && pattern_length > 0 {
+        let data_bytes: Vec<u8> = data_content.iter().flat_map(|word| word.to_le_bytes().to_vec()).take(data_length/8).collect();
+        let pattern_bytes: Vec<u8> = pattern.iter().flat_map(|word| word.to_le_bytes().to_vec()).take(pattern_length/8).collect();
+        return data_bytes[start_offset/8..].windows(pattern_bytes.len())
+            .position(|window| window[0] == pattern_bytes[0] && window == &pattern_bytes[..])
+            .map(|position| start_offset+position*8);
+    }
+    let pattern_words: Vec<usize> = bitops::BitwiseRead::new(pattern, pattern_length, 0).collect();
+    let step = if byte_aligned { 8 } else { 1 };
+    (start_offset..=data_length-pattern_length).step_by(step).find(|offset| {
+        bitops::BitwiseRead::new(data_content, pattern_length, *offset).eq(pattern_words.iter().cloned())
+    })
+}
+
+fn find_in_content(symbol_handle: &SymbolHandle, pattern: &[usize], pattern_length: usize, start_offset: usize, byte_aligned: bool) -> Option<usize> {
+    let start_offset = if byte_aligned { start_offset.next_multiple_of(8) } else { start_offset };
+    let data_length = symbol_handle.data_length;
+    if start_offset+pattern_length > data_length {
+        return None;
+    }
+    let data_content = symbol_handle.data_content.borrow();
+    (start_offset..=data_length-pattern_length).step_by(SEARCH_WINDOW_LENGTH).find_map(|window_offset| {
+        let window_length = (SEARCH_WINDOW_LENGTH+pattern_length-1).min(data_length-window_offset);
+        find_in_window(&data_content.read(window_offset, window_length), window_length, pattern, pattern_length, 0, byte_aligned).map(|offset| window_offset+offset)
+    })
+}
+
+fn find_in_data_with(symbol: symbol::Symbol, pattern: &[usize], pattern_length: usize, start_offset: usize, byte_aligned: bool) -> Option<usize> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let symbol_handle = get_symbol_handle(&namespace_index, symbol)?;
+        find_in_content(symbol_handle, pattern, pattern_length, start_offset, byte_aligned)
+    })
+}
+
+pub fn find_in_data(symbol: symbol::Symbol, pattern: &[usize], pattern_length: usize, start_offset: usize) -> Option<usize> {
+    find_in_data_with(symbol, pattern, pattern_length, start_offset, false)
+}
+
+pub fn find_in_data_byte_aligned(symbol: symbol::Symbol, pattern: &[usize], pattern_length: usize, start_offset: usize) -> Option<usize> {
+    find_in_data_with(symbol, pattern, pattern_length, start_offset, true)
+}
+
+pub fn find_in_namespace(namespace_identity: symbol::Identity, pattern: &[usize], pattern_length: usize, byte_aligned: bool) -> Vec<(symbol::Symbol, usize)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut result: Vec<(symbol::Symbol, usize)> = vec![];
+        if let Some(namespace_handle) = namespace_index.get(&namespace_identity) {
+            for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
+                if let Some(offset) = find_in_content(symbol_handle, pattern, pattern_length, 0, byte_aligned) {
+                    result.push((symbol::Symbol(namespace_identity, *symbol_identity), offset));
+                }
+            }
+        }
+        if ordered_results() {
+            result.sort_unstable();
+        }
+        result
+    })
+}
+
+
+fn resize_histogram(histogram: &mut Vec<usize>, previous: usize, next: usize) {
+    if previous > 0 {
+        histogram[previous] -= 1;
+    }
+    if next > 0 {
+        if histogram.len() <= next {
+            histogram.resize(next+1, 0);
+        }
+        histogram[next] += 1;
+    }
+    while histogram.last() == Some(&0) {
+        histogram.pop();
+    }
+}
 
-fn set_triple_subindex(beta_self: &mut BetaCollection, beta: symbol::Symbol, gamma: symbol::Symbol, linked: bool) -> bool {
+fn set_triple_subindex(subindex: usize, beta_self: &mut BetaCollection, beta: symbol::Symbol, gamma: symbol::Symbol, linked: bool) -> bool {
+    let previous_beta_count = beta_self.len();
+    let previous_gamma_count = beta_self.get(&beta).map_or(0, |gamma_self| gamma_self.len());
     if linked {
         match beta_self.get_mut(&beta) {
-            Some(gamma_self) => { gamma_self.insert(gamma) },
+            Some(gamma_self) => {
+                if !gamma_self.insert(gamma) {
+                    return false;
+                }
+            },
             None => {
                 let mut gamma_self = GammaCollection::new();
                 assert!(gamma_self.insert(gamma));
                 assert!(beta_self.insert(beta, gamma_self).is_none());
-                true
             }
         }
     } else {
@@ -317,15 +658,21 @@ fn set_triple_subindex(beta_self: &mut BetaCollection, beta: symbol::Symbol, gam
                 if gamma_self.is_empty() {
                     assert!(beta_self.remove(&beta).is_some());
                 }
-                true
             },
-            None => false
+            None => { return false; }
         }
     }
+    let gamma_count = if linked { previous_gamma_count+1 } else { previous_gamma_count-1 };
+    GAMMA_SIZES.with(|gamma_sizes| resize_histogram(&mut gamma_sizes.borrow_mut()[subindex], previous_gamma_count, gamma_count));
+    if beta_self.len() != previous_beta_count {
+        BETA_SIZES.with(|beta_sizes| resize_histogram(&mut beta_sizes.borrow_mut()[subindex], previous_beta_count, beta_self.len()));
+    }
+    true
 }
 
 
 
+#[allow(clippy::needless_range_loop)]
 fn set_triple_internal(namespace_index: &mut NamespaceIndex, triple: Triple, linked: bool) -> bool {
     for triple_index in 0..3 {
         if get_symbol_handle_mut(namespace_index, triple[triple_index]).is_none() {
@@ -335,19 +682,71 @@ fn set_triple_internal(namespace_index: &mut NamespaceIndex, triple: Triple, lin
     let mut result: bool = false;
     for triple_index in 0..3 {
         let entity_handle = get_symbol_handle_mut(namespace_index, triple[triple_index]).unwrap();
-        result |= set_triple_subindex(&mut entity_handle.subindices[triple_index], triple[(triple_index+1)%3], triple[(triple_index+2)%3], linked);
-        result |= set_triple_subindex(&mut entity_handle.subindices[triple_index+3], triple[(triple_index+2)%3], triple[(triple_index+1)%3], linked);
+        result |= set_triple_subindex(triple_index, &mut entity_handle.subindices[triple_index], triple[(triple_index+1)%3], triple[(triple_index+2)%3], linked);
+        result |= set_triple_subindex(triple_index+3, &mut entity_handle.subindices[triple_index+3], triple[(triple_index+2)%3], triple[(triple_index+1)%3], linked);
+    }
+    if result {
+        TRIPLE_COUNT.with(|triple_count| triple_count.set(if linked { triple_count.get()+1 } else { triple_count.get()-1 }));
+        advance_generation();
     }
     result
 }
 
 pub fn set_triple(triple: Triple, linked: bool) -> bool {
-    NAMESPACE_INDEX.with(|namespace_index_cell| {
+    let result = NAMESPACE_INDEX.with(|namespace_index_cell| {
         let mut namespace_index = namespace_index_cell.borrow_mut();
         set_triple_internal(&mut namespace_index, triple, linked)
-    })
+    });
+    if result {
+        history::record(history::Operation::SetTriple(triple, linked));
+        views::triple_changed(triple, linked);
+        rules::triple_changed(triple);
+    }
+    result
+}
+
+pub fn apply_operation(operation: &history::Operation, reverse: bool) -> bool {
+    let result = NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let mut namespace_index = namespace_index_cell.borrow_mut();
+        match operation {
+            history::Operation::ManifestSymbol(symbol) | history::Operation::ReleaseSymbol(symbol) => {
+                if reverse == matches!(operation, history::Operation::ManifestSymbol(_)) {
+                    release_symbol_internal(&mut namespace_index, *symbol)
+                } else {
+                    manifest_symbol_internal(&mut namespace_index, *symbol)
+                }
+            },
+            history::Operation::SetTriple(triple, linked) => {
+                set_triple_internal(&mut namespace_index, *triple, *linked != reverse)
+            },
+            history::Operation::IncreaseLength(symbol, offset, length) => {
+                let length = if reverse { -(*length as isize) } else { *length as isize };
+                crease_length_internal(&mut namespace_index, *symbol, *offset, length)
+            },
+            history::Operation::DecreaseLength(symbol, offset, length, removed_data) => {
+                if reverse {
+                    crease_length_internal(&mut namespace_index, *symbol, *offset, *length as isize) &&
+                    write_data_internal(&mut namespace_index, *symbol, *offset, *length, removed_data)
+                } else {
+                    crease_length_internal(&mut namespace_index, *symbol, *offset, -(*length as isize))
+                }
+            },
+            history::Operation::WriteData(symbol, offset, length, previous_data, written_data) => {
+                write_data_internal(&mut namespace_index, *symbol, *offset, *length, if reverse { previous_data } else { written_data })
+            }
+        }
+    });
+    if !result {
+        return false;
+    }
+    if let history::Operation::SetTriple(triple, linked) = operation {
+        views::triple_changed(*triple, *linked != reverse);
+        rules::triple_changed(*triple);
+    }
+    true
 }
 
+#[allow(clippy::single_match)]
 pub fn query_symbols(namespace_identity: symbol::Identity) -> Vec<symbol::Identity> {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let namespace_index = namespace_index_cell.borrow();
@@ -360,145 +759,642 @@ pub fn query_symbols(namespace_identity: symbol::Identity) -> Vec<symbol::Identi
             },
             None => {}
         }
+        if ordered_results() {
+            result.sort_unstable();
+        }
+        result
+    })
+}
+
+fn allocated_identities(namespace_handle: Option<&NamespaceHandle>, start: Option<symbol::Identity>) -> impl Iterator<Item=symbol::Identity>+'_ {
+    let mut next = start;
+    std::iter::from_fn(move || {
+        let identity = namespace_handle?.free_pool.next_allocated(next?)?;
+        next = identity.checked_add(1);
+        Some(identity)
+    })
+}
+
+fn symbols_from(namespace_index: &NamespaceIndex, start: symbol::Symbol) -> impl Iterator<Item=(symbol::Symbol, &SymbolHandle)> {
+    allocated_identities(namespace_index.get(&META_NAMESPACE_IDENTITY), Some(start.0)).flat_map(move |namespace_identity| {
+        let namespace_handle = namespace_index.get(&namespace_identity);
+        allocated_identities(namespace_handle, Some(if namespace_identity == start.0 { start.1 } else { 0 }))
+            .filter_map(move |symbol_identity| namespace_handle?.symbol_index.get(&symbol_identity).map(|symbol_handle| (symbol::Symbol(namespace_identity, symbol_identity), symbol_handle)))
+    })
+}
+
+pub fn query_symbols_page(namespace_identity: symbol::Identity, token: ContinuationToken, limit: usize) -> Option<(Vec<symbol::Identity>, Option<ContinuationToken>)> {
+    token.paginate(|key| {
+        let start = match key {
+            [] => Some(0),
+            [last] => last.checked_add(1),
+            _ => { return None; }
+        };
+        NAMESPACE_INDEX.with(|namespace_index_cell| {
+            let namespace_index = namespace_index_cell.borrow();
+            let mut symbols = allocated_identities(namespace_index.get(&namespace_identity), start);
+            let page: Vec<symbol::Identity> = symbols.by_ref().take(limit).collect();
+            let next_key = if symbols.next().is_some() { Some(page.last().map_or(key.to_vec(), |last| vec![*last])) } else { None };
+            Some((page, next_key))
+        })
+    })
+}
+
+pub fn query_gammas(triple_index: TripleIndex, alpha: symbol::Symbol, beta: symbol::Symbol) -> Vec<symbol::Symbol> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut result: Vec<symbol::Symbol> = match get_symbol_handle(&namespace_index, alpha) {
+            Some(symbol_handle) => symbol_handle.subindices[triple_index as usize].get(&beta).map_or(vec![], |gamma_self| gamma_self.iter().cloned().collect()),
+            None => vec![]
+        };
+        if ordered_results() {
+            result.sort_unstable();
+        }
         result
     })
 }
 
+#[allow(clippy::needless_return)]
 fn reorder_triple(order: &TriplePermutation, triple_index: TripleIndex, triple: &Triple) -> Triple {
     let index = triple_index as usize;
     return [triple[order[0][index]], triple[order[1][index]], triple[order[2][index]]];
 }
 
-pub fn query_triples(mask: usize, mut triple: Triple) -> Vec<Triple> {
+pub fn triple_index(mask: usize) -> TripleIndex {
+    INDEX_LOOKUP[mask]
+}
+
+pub fn count_triples(mask: usize, mut triple: Triple) -> usize {
     NAMESPACE_INDEX.with(|namespace_index_cell| {
         let namespace_index = namespace_index_cell.borrow();
         let triple_index = INDEX_LOOKUP[mask];
-        triple = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &triple);
-        let mut result: Vec<Triple> = vec![];
         match SEARCH_LOOKUP[mask] {
-            TripleQueryFunc::SearchMMM => {
-                match get_symbol_handle(&namespace_index, triple[0]) {
-                    Some(symbol_handle) => {
-                        match symbol_handle.subindices[triple_index as usize].get(&triple[1]) {
-                            Some(gamma_self) => {
-                                if gamma_self.contains(&triple[2]) {
-                                    result.push(triple);
-                                }
-                            },
-                            None => {}
-                        }
-                    },
-                    None => {}
-                }
-            },
-            TripleQueryFunc::SearchMMI => {
-                match get_symbol_handle(&namespace_index, triple[0]) {
-                    Some(symbol_handle) => {
-                        if symbol_handle.subindices[triple_index as usize].contains_key(&triple[1]) {
-                            result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
-                        }
-                    },
-                    None => {}
-                }
-            },
-            TripleQueryFunc::SearchMII => {
-                match get_symbol_handle(&namespace_index, triple[0]) {
-                    Some(symbol_handle) => {
-                        if !symbol_handle.subindices[triple_index as usize].is_empty() {
-                            result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
-                        }
-                    },
-                    None => {}
-                }
+            TripleQueryFunc::SearchIII | TripleQueryFunc::SearchVII => {
+                return query_triples_internal(&namespace_index, mask, triple).count();
             },
-            TripleQueryFunc::SearchIII => {
-                for namespace_handle in namespace_index.values() {
-                    for symbol_handle in namespace_handle.symbol_index.values() {
-                        if !symbol_handle.subindices[triple_index as usize].is_empty() {
-                            result.push(triple);
-                            break;
-                        }
-                    }
-                }
+            TripleQueryFunc::SearchVVI => {
+                return namespace_index.values().map(|namespace_handle| {
+                    namespace_handle.symbol_index.values().map(|symbol_handle| symbol_handle.subindices[triple_index as usize].len()).sum::<usize>()
+                }).sum();
             },
-            TripleQueryFunc::SearchMMV => {
-                match get_symbol_handle(&namespace_index, triple[0]) {
-                    Some(symbol_handle) => {
-                        match symbol_handle.subindices[triple_index as usize].get(&triple[1]) {
-                            Some(gamma_self) => {
-                                for gamma in gamma_self.iter() {
-                                    triple[2] = *gamma;
-                                    result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
-                                }
-                            },
-                            None => {}
-                        }
-                    },
-                    None => {}
-                }
+            TripleQueryFunc::SearchVVV => {
+                return TRIPLE_COUNT.with(|triple_count| triple_count.get());
             },
-            TripleQueryFunc::SearchMVV => {
-                match get_symbol_handle(&namespace_index, triple[0]) {
-                    Some(symbol_handle) => {
-                        for (beta, gamma_self) in symbol_handle.subindices[triple_index as usize].iter() {
-                            triple[1] = *beta;
-                            for gamma in gamma_self.iter() {
-                                triple[2] = *gamma;
-                                result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
-                            }
-                        }
+            _ => {}
+        }
+        triple = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &triple);
+        let subindex = match get_symbol_handle(&namespace_index, triple[0]) {
+            Some(symbol_handle) => &symbol_handle.subindices[triple_index as usize],
+            None => { return 0; }
+        };
+        match SEARCH_LOOKUP[mask] {
+            TripleQueryFunc::SearchMMM => subindex.get(&triple[1]).map_or(0, |gamma_self| gamma_self.contains(&triple[2]) as usize),
+            TripleQueryFunc::SearchMMI => subindex.contains_key(&triple[1]) as usize,
+            TripleQueryFunc::SearchMII => !subindex.is_empty() as usize,
+            TripleQueryFunc::SearchMMV => subindex.get(&triple[1]).map_or(0, |gamma_self| gamma_self.len()),
+            TripleQueryFunc::SearchMVI => subindex.len(),
+            _ => subindex.values().map(|gamma_self| gamma_self.len()).sum()
+        }
+    })
+}
+
+pub fn estimate_triples(mask: usize, triple: Triple) -> usize {
+    match SEARCH_LOOKUP[mask] {
+        TripleQueryFunc::SearchIII => 1,
+        TripleQueryFunc::SearchVII | TripleQueryFunc::SearchVVI | TripleQueryFunc::SearchVVV => TRIPLE_COUNT.with(|triple_count| triple_count.get()),
+        _ => count_triples(mask, triple)
+    }
+}
+
+pub fn average_matches(mask: usize) -> usize {
+    let subindex = INDEX_LOOKUP[mask] as usize;
+    let prefix_count = match (0..3).filter(|triple_index| (mask/[1, 3, 9][*triple_index])%3 == QueryMask::Match as usize).count() {
+        0 => 1,
+        1 => BETA_SIZES.with(|beta_sizes| beta_sizes.borrow()[subindex].iter().sum()),
+        2 => GAMMA_SIZES.with(|gamma_sizes| gamma_sizes.borrow()[subindex].iter().sum()),
+        _ => { return 1; }
+    };
+    if prefix_count == 0 {
+        return 0;
+    }
+    TRIPLE_COUNT.with(|triple_count| triple_count.get()).div_ceil(prefix_count)
+}
+
+fn restricted_namespaces<'a>(namespace_index: &'a NamespaceIndex, namespaces: Option<&'a HashSet<symbol::Identity>>) -> Box<dyn Iterator<Item=(&'a symbol::Identity, &'a NamespaceHandle)>+'a> {
+    match namespaces {
+        Some(namespaces) => Box::new(namespaces.iter().filter_map(move |namespace_identity| namespace_index.get_key_value(namespace_identity))),
+        None => Box::new(namespace_index.iter())
+    }
+}
+
+fn query_triples_internal<'a>(namespace_index: &'a NamespaceIndex, mask: usize, mut triple: Triple) -> Box<dyn Iterator<Item=Triple>+'a> {
+    let triple_index = INDEX_LOOKUP[mask];
+    let subindex = triple_index as usize;
+    triple = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &triple);
+    let normalize = move |triple: Triple| reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple);
+    let beta_self = get_symbol_handle(namespace_index, triple[0]).map(|symbol_handle| &symbol_handle.subindices[subindex]);
+    match SEARCH_LOOKUP[mask] {
+        TripleQueryFunc::SearchMMM => {
+            Box::new(beta_self.and_then(|beta_self| beta_self.get(&triple[1]))
+                     .filter(|gamma_self| gamma_self.contains(&triple[2]))
+                     .map(|_gamma_self| triple).into_iter())
+        },
+        TripleQueryFunc::SearchMMI => {
+            Box::new(beta_self.filter(|beta_self| beta_self.contains_key(&triple[1]))
+                     .map(|_beta_self| normalize(triple)).into_iter())
+        },
+        TripleQueryFunc::SearchMII => {
+            Box::new(beta_self.filter(|beta_self| !beta_self.is_empty())
+                     .map(|_beta_self| normalize(triple)).into_iter())
+        },
+        TripleQueryFunc::SearchIII => {
+            Box::new(namespace_index.values()
+                     .filter(move |namespace_handle| namespace_handle.symbol_index.values().any(|symbol_handle| !symbol_handle.subindices[subindex].is_empty()))
+                     .map(move |_namespace_handle| triple))
+        },
+        TripleQueryFunc::SearchMMV => {
+            Box::new(beta_self.and_then(|beta_self| beta_self.get(&triple[1])).into_iter()
+                     .flat_map(|gamma_self| gamma_self.iter())
+                     .map(move |gamma| normalize([triple[0], triple[1], *gamma])))
+        },
+        TripleQueryFunc::SearchMVV => {
+            Box::new(beta_self.into_iter()
+                     .flat_map(|beta_self| beta_self.iter())
+                     .flat_map(move |(beta, gamma_self)| gamma_self.iter().map(move |gamma| normalize([triple[0], *beta, *gamma]))))
+        },
+        TripleQueryFunc::SearchMVI => {
+            Box::new(beta_self.into_iter()
+                     .flat_map(|beta_self| beta_self.keys())
+                     .map(move |beta| normalize([triple[0], *beta, triple[2]])))
+        },
+        TripleQueryFunc::SearchVII => {
+            Box::new(namespace_index.iter()
+                     .flat_map(move |(namespace_identity, namespace_handle)| namespace_handle.symbol_index.iter()
+                         .filter(move |(_symbol_identity, symbol_handle)| !symbol_handle.subindices[subindex].is_empty())
+                         .map(move |(symbol_identity, _symbol_handle)| normalize([symbol::Symbol(*namespace_identity, *symbol_identity), triple[1], triple[2]]))))
+        },
+        TripleQueryFunc::SearchVVI => {
+            Box::new(namespace_index.iter()
+                     .flat_map(move |(namespace_identity, namespace_handle)| namespace_handle.symbol_index.iter()
+                         .flat_map(move |(symbol_identity, symbol_handle)| symbol_handle.subindices[subindex].keys()
+                             .map(move |beta| normalize([symbol::Symbol(*namespace_identity, *symbol_identity), *beta, triple[2]])))))
+        },
+        TripleQueryFunc::SearchVVV => {
+            Box::new(namespace_index.iter()
+                     .flat_map(move |(namespace_identity, namespace_handle)| namespace_handle.symbol_index.iter()
+                         .flat_map(move |(symbol_identity, symbol_handle)| symbol_handle.subindices[subindex].iter()
+                             .flat_map(move |(beta, gamma_self)| gamma_self.iter()
+                                 .map(move |gamma| [symbol::Symbol(*namespace_identity, *symbol_identity), *beta, *gamma])))))
+        }
+    }
+}
+
+pub fn query_triples_with<R, F: FnOnce(&mut dyn Iterator<Item=Triple>) -> R>(mask: usize, triple: Triple, f: F) -> R {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut triples = query_triples_internal(&namespace_index, mask, triple);
+        f(&mut triples)
+    })
+}
+
+pub fn query_triples(mask: usize, triple: Triple) -> Vec<Triple> {
+    let mut result: Vec<Triple> = query_triples_with(mask, triple, |triples| triples.collect());
+    if ordered_results() {
+        result.sort_unstable();
+    }
+    result
+}
+
+pub type NamespaceRestriction = [Option<HashSet<symbol::Identity>>; 3];
+
+#[cfg(test)]
+thread_local!(static VISITED_TRIPLES: Cell<usize> = const { Cell::new(0) });
+
+fn record_visit() {
+    #[cfg(test)]
+    VISITED_TRIPLES.with(|visited_triples| visited_triples.set(visited_triples.get()+1));
+}
+
+fn query_triples_restricted<'a>(namespace_index: &'a NamespaceIndex, mask: usize, triple: Triple, namespaces: &'a NamespaceRestriction) -> Box<dyn Iterator<Item=Triple>+'a> {
+    const VARYING: usize = QueryMask::Varying as usize;
+    const MATCH: usize = QueryMask::Match as usize;
+    let masks: Vec<usize> = (0..3).map(|triple_index| (mask/[1, 3, 9][triple_index])%3).collect();
+    let mut positions: Vec<usize> = (0..3).collect();
+    positions.sort_by_key(|triple_index| (masks[*triple_index], namespaces[*triple_index].is_none(), *triple_index));
+    let subindex = (0..6).find(|subindex| (0..3).all(|level| TRIPLE_PRIORITIZED[level][*subindex] == positions[level])).unwrap();
+    let triple_index = TRIPLE_INDICES[subindex];
+    let levels = [masks[positions[0]], masks[positions[1]], masks[positions[2]]];
+    let restrictions: [Option<&HashSet<symbol::Identity>>; 3] = std::array::from_fn(|level| namespaces[positions[level]].as_ref().filter(|_namespaces| levels[level] == VARYING));
+    let admits = move |level: usize, symbol: &symbol::Symbol| restrictions[level].is_none_or(|namespaces| namespaces.contains(&symbol.0));
+    let prefix = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &triple);
+    let normalize = move |triple: Triple| reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple);
+    let alphas: Box<dyn Iterator<Item=(symbol::Symbol, &SymbolHandle)>> = if levels[0] == VARYING {
+        Box::new(restricted_namespaces(namespace_index, restrictions[0])
+                 .flat_map(|(namespace_identity, namespace_handle)| namespace_handle.symbol_index.iter()
+                     .map(move |(symbol_identity, symbol_handle)| (symbol::Symbol(*namespace_identity, *symbol_identity), symbol_handle))))
+    } else {
+        Box::new(get_symbol_handle(namespace_index, prefix[0]).map(|symbol_handle| (prefix[0], symbol_handle)).into_iter())
+    };
+    Box::new(alphas.flat_map(move |(alpha, symbol_handle)| {
+        let beta_self = &symbol_handle.subindices[subindex];
+        let betas: Box<dyn Iterator<Item=(symbol::Symbol, Option<&GammaCollection>)>> = match levels[1] {
+            MATCH => Box::new(beta_self.get(&prefix[1]).map(|gamma_self| (prefix[1], Some(gamma_self))).into_iter()),
+            VARYING => Box::new(beta_self.iter().filter(move |(beta, _gamma_self)| admits(1, beta)).map(|(beta, gamma_self)| (*beta, Some(gamma_self)))),
+            _ => Box::new(Some((prefix[1], None)).filter(|_beta| !beta_self.is_empty()).into_iter())
+        };
+        betas.flat_map(move |(beta, gamma_self)| {
+            let gammas: Box<dyn Iterator<Item=symbol::Symbol>> = match levels[2] {
+                MATCH => Box::new(gamma_self.filter(|gamma_self| gamma_self.contains(&prefix[2])).map(|_gamma_self| prefix[2]).into_iter()),
+                VARYING => Box::new(gamma_self.into_iter().flatten().copied()),
+                _ => Box::new(std::iter::once(prefix[2]))
+            };
+            gammas.inspect(|_gamma| record_visit()).filter(move |gamma| admits(2, gamma)).map(move |gamma| normalize([alpha, beta, gamma]))
+        })
+    }))
+}
+
+pub fn query_triples_in_namespaces(mask: usize, triple: Triple, namespaces: &NamespaceRestriction) -> Vec<Triple> {
+    let mut result: Vec<Triple> = NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let result = query_triples_restricted(&namespace_index, mask, triple, namespaces).collect();
+        result
+    });
+    if ordered_results() {
+        result.sort_unstable();
+    }
+    result
+}
+
+fn successor(symbol: symbol::Symbol) -> Option<symbol::Symbol> {
+    match symbol.1.checked_add(1) {
+        Some(symbol_identity) => Some(symbol::Symbol(symbol.0, symbol_identity)),
+        None => symbol.0.checked_add(1).map(|namespace_identity| symbol::Symbol(namespace_identity, 0))
+    }
+}
+
+fn sorted_keys<'a, I: Iterator<Item=&'a symbol::Symbol>>(keys: I, start: Option<symbol::Symbol>) -> impl Iterator<Item=symbol::Symbol> {
+    let mut heap: BinaryHeap<Reverse<symbol::Symbol>> = keys.filter(|key| start.is_none_or(|start| **key >= start)).map(|key| Reverse(*key)).collect();
+    std::iter::from_fn(move || heap.pop().map(|Reverse(key)| key))
+}
+
+fn query_triples_from<'a>(namespace_index: &'a NamespaceIndex, mask: usize, triple: Triple, after: Option<Triple>) -> Box<dyn Iterator<Item=Triple>+'a> {
+    const VARYING: usize = QueryMask::Varying as usize;
+    const MATCH: usize = QueryMask::Match as usize;
+    let masks: Vec<usize> = (0..3).map(|triple_index| (mask/[1, 3, 9][triple_index])%3).collect();
+    let mut positions: Vec<usize> = (0..3).collect();
+    positions.sort_by_key(|triple_index| (masks[*triple_index], *triple_index));
+    let subindex = (0..6).find(|subindex| (0..3).all(|level| TRIPLE_PRIORITIZED[level][*subindex] == positions[level])).unwrap();
+    let triple_index = TRIPLE_INDICES[subindex];
+    let levels = [masks[positions[0]], masks[positions[1]], masks[positions[2]]];
+    let prefix = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &triple);
+    let start = match after {
+        Some(after) => {
+            let mut start = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &after);
+            let mut carry = true;
+            for level in (0..3).rev().filter(|level| levels[*level] == VARYING) {
+                match successor(start[level]) {
+                    Some(next) => {
+                        start[level] = next;
+                        carry = false;
+                        break;
                     },
-                    None => {}
+                    None => { start[level] = symbol::Symbol(0, 0); }
                 }
-            },
-            TripleQueryFunc::SearchMVI => {
-                match get_symbol_handle(&namespace_index, triple[0]) {
-                    Some(symbol_handle) => {
-                        for beta in symbol_handle.subindices[triple_index as usize].keys() {
-                            triple[1] = *beta;
-                            result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
-                        }
-                    },
-                    None => {}
+            }
+            if carry {
+                return Box::new(std::iter::empty());
+            }
+            Some(start)
+        },
+        None => None
+    };
+    let normalize = move |triple: Triple| reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple);
+    let alphas: Box<dyn Iterator<Item=(symbol::Symbol, &SymbolHandle, bool)>> = if levels[0] == VARYING {
+        Box::new(symbols_from(namespace_index, start.map_or(symbol::Symbol(0, 0), |start| start[0]))
+                 .map(move |(alpha, symbol_handle)| (alpha, symbol_handle, start.is_some_and(|start| start[0] == alpha))))
+    } else {
+        Box::new(get_symbol_handle(namespace_index, prefix[0]).map(|symbol_handle| (prefix[0], symbol_handle, start.is_some())).into_iter())
+    };
+    Box::new(alphas.flat_map(move |(alpha, symbol_handle, boundary)| {
+        let beta_self = &symbol_handle.subindices[subindex];
+        let betas: Box<dyn Iterator<Item=(symbol::Symbol, Option<&GammaCollection>, bool)>> = match levels[1] {
+            MATCH => Box::new(beta_self.get(&prefix[1]).map(|gamma_self| (prefix[1], Some(gamma_self), boundary)).into_iter()),
+            VARYING => Box::new(sorted_keys(beta_self.keys(), start.filter(|_start| boundary).map(|start| start[1]))
+                                .map(move |beta| (beta, beta_self.get(&beta), boundary && start.is_some_and(|start| start[1] == beta)))),
+            _ => Box::new(Some((prefix[1], None, boundary)).filter(|_beta| !beta_self.is_empty()).into_iter())
+        };
+        betas.flat_map(move |(beta, gamma_self, boundary)| {
+            let gammas: Box<dyn Iterator<Item=symbol::Symbol>> = match levels[2] {
+                MATCH => Box::new(gamma_self.filter(|gamma_self| gamma_self.contains(&prefix[2])).map(|_gamma_self| prefix[2]).into_iter()),
+                VARYING => Box::new(sorted_keys(gamma_self.into_iter().flatten(), start.filter(|_start| boundary).map(|start| start[2]))),
+                _ => Box::new(std::iter::once(prefix[2]))
+            };
+            gammas.map(move |gamma| normalize([alpha, beta, gamma]))
+        })
+    }))
+}
+
+pub fn query_triples_after(mask: usize, triple: Triple, key: &[usize], limit: usize) -> Option<(Vec<Triple>, Option<Vec<usize>>)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        if (0..3).all(|triple_index| (mask/[1, 3, 9][triple_index])%3 != QueryMask::Varying as usize) {
+            let skipped = match key {
+                [] => 0,
+                [skipped] => *skipped,
+                _ => { return None; }
+            };
+            let mut triples = query_triples_internal(&namespace_index, mask, triple).skip(skipped);
+            let page: Vec<Triple> = triples.by_ref().take(limit).collect();
+            let next_key = if triples.next().is_some() { Some(vec![skipped+page.len()]) } else { None };
+            return Some((page, next_key));
+        }
+        let after = match key {
+            [] => None,
+            [entity_namespace, entity, attribute_namespace, attribute, value_namespace, value] => Some([
+                symbol::Symbol(*entity_namespace, *entity),
+                symbol::Symbol(*attribute_namespace, *attribute),
+                symbol::Symbol(*value_namespace, *value)
+            ]),
+            _ => { return None; }
+        };
+        let mut triples = query_triples_from(&namespace_index, mask, triple, after);
+        let page: Vec<Triple> = triples.by_ref().take(limit).collect();
+        let next_key = if triples.next().is_some() {
+            Some(page.last().map_or(key.to_vec(), |last| vec![last[0].0, last[0].1, last[1].0, last[1].1, last[2].0, last[2].1]))
+        } else { None };
+        Some((page, next_key))
+    })
+}
+
+pub fn query_triples_page(mask: usize, triple: Triple, token: ContinuationToken, limit: usize) -> Option<(Vec<Triple>, Option<ContinuationToken>)> {
+    token.paginate(|key| query_triples_after(mask, triple, key, limit))
+}
+
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct SymbolStatistics {
+    pub triple_counts: [usize; 6],
+    pub beta_counts: [usize; 6],
+    pub data_length: usize
+}
+
+impl SymbolStatistics {
+    fn new(symbol_handle: &SymbolHandle) -> Self {
+        let mut statistics = Self{data_length: symbol_handle.data_length, ..Default::default()};
+        for (subindex, beta_self) in symbol_handle.subindices.iter().enumerate() {
+            statistics.triple_counts[subindex] = beta_self.values().map(|gamma_self| gamma_self.len()).sum();
+            statistics.beta_counts[subindex] = beta_self.len();
+        }
+        statistics
+    }
+
+    pub fn as_entity(&self) -> usize {
+        self.triple_counts[TripleIndex::EAV as usize]
+    }
+
+    pub fn as_attribute(&self) -> usize {
+        self.triple_counts[TripleIndex::AVE as usize]
+    }
+
+    pub fn as_value(&self) -> usize {
+        self.triple_counts[TripleIndex::VEA as usize]
+    }
+
+    pub fn degree(&self) -> usize {
+        self.as_entity()+self.as_attribute()+self.as_value()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct NamespaceStatistics {
+    pub symbol_count: usize,
+    pub triple_counts: [usize; 6],
+    pub data_length: usize,
+    pub max_degree: usize
+}
+
+pub fn symbol_statistics(symbol: symbol::Symbol) -> Option<SymbolStatistics> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        get_symbol_handle(&namespace_index, symbol).map(SymbolStatistics::new)
+    })
+}
+
+pub fn namespace_statistics(namespace_identity: symbol::Identity) -> Option<NamespaceStatistics> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let namespace_handle = namespace_index.get(&namespace_identity)?;
+        let mut statistics = NamespaceStatistics{symbol_count: namespace_handle.symbol_index.len(), ..Default::default()};
+        for symbol_handle in namespace_handle.symbol_index.values() {
+            let symbol_statistics = SymbolStatistics::new(symbol_handle);
+            for subindex in 0..6 {
+                statistics.triple_counts[subindex] += symbol_statistics.triple_counts[subindex];
+            }
+            statistics.data_length += symbol_statistics.data_length;
+            statistics.max_degree = statistics.max_degree.max(symbol_statistics.degree());
+        }
+        Some(statistics)
+    })
+}
+
+pub fn top_symbols_by_degree(namespace_identity: Option<symbol::Identity>, count: usize) -> Vec<(symbol::Symbol, usize)> {
+    NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let mut heap: BinaryHeap<Reverse<(usize, Reverse<symbol::Symbol>)>> = BinaryHeap::with_capacity(count+1);
+        let namespaces: Box<dyn Iterator<Item=(&symbol::Identity, &NamespaceHandle)>> = match namespace_identity {
+            Some(namespace_identity) => Box::new(namespace_index.get_key_value(&namespace_identity).into_iter()),
+            None => Box::new(namespace_index.iter())
+        };
+        for (namespace_identity, namespace_handle) in namespaces {
+            for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
+                let degree = SymbolStatistics::new(symbol_handle).degree();
+                heap.push(Reverse((degree, Reverse(symbol::Symbol(*namespace_identity, *symbol_identity)))));
+                if heap.len() > count {
+                    heap.pop();
                 }
-            },
-            TripleQueryFunc::SearchVII => {
-                for (namespace_identity, namespace_handle) in namespace_index.iter() {
-                    for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
-                        if symbol_handle.subindices[triple_index as usize].is_empty() {
-                            continue;
-                        }
-                        triple[0] = symbol::Symbol{0: *namespace_identity, 1: *symbol_identity};
-                        result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
+            }
+        }
+        heap.into_sorted_vec().into_iter().map(|Reverse((degree, Reverse(symbol)))| (symbol, degree)).collect()
+    })
+}
+
+
+
+const SAMPLING_ATTEMPTS_PER_ITEM: usize = 1024;
+
+fn sample_sorted<T: Ord>(mut items: Vec<T>, count: usize, random: &mut random::SplitMix64) -> Vec<T> {
+    items.sort_unstable();
+    random.shuffle_prefix(&mut items, count);
+    items.truncate(count);
+    items
+}
+
+pub fn sample_symbols(namespace_identity: symbol::Identity, count: usize, seed: u64) -> Vec<symbol::Symbol> {
+    let mut random = random::SplitMix64::new(seed);
+    let identities: Vec<symbol::Identity> = NAMESPACE_INDEX.with(|namespace_index_cell| {
+        let namespace_index = namespace_index_cell.borrow();
+        let namespace_handle = match namespace_index.get(&namespace_identity) {
+            Some(namespace_handle) => namespace_handle,
+            None => { return vec![]; }
+        };
+        let population = namespace_handle.symbol_index.len();
+        let count = count.min(population);
+        if count.saturating_mul(2) < population {
+            let upper_bound = namespace_handle.free_pool.upper_bound();
+            let mut chosen: HashSet<symbol::Identity> = HashSet::new();
+            let mut result: Vec<symbol::Identity> = vec![];
+            for _attempt in 0..count.saturating_mul(SAMPLING_ATTEMPTS_PER_ITEM) {
+                let symbol_identity = random.below(upper_bound);
+                if namespace_handle.symbol_index.contains_key(&symbol_identity) && chosen.insert(symbol_identity) {
+                    result.push(symbol_identity);
+                    if result.len() == count {
+                        return result;
                     }
                 }
-            },
-            TripleQueryFunc::SearchVVI => {
-                for (namespace_identity, namespace_handle) in namespace_index.iter() {
-                    for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
-                        triple[0] = symbol::Symbol{0: *namespace_identity, 1: *symbol_identity};
-                        for beta in symbol_handle.subindices[triple_index as usize].keys() {
-                            triple[1] = *beta;
-                            result.push(reorder_triple(&TRIPLE_NORMALIZED, triple_index, &triple));
+            }
+        }
+        sample_sorted(namespace_handle.symbol_index.keys().cloned().collect(), count, &mut random)
+    });
+    identities.into_iter().map(|symbol_identity| symbol::Symbol(namespace_identity, symbol_identity)).collect()
+}
+
+pub fn sample_triples(mask: usize, triple: Triple, count: usize, seed: u64) -> Vec<Triple> {
+    const VARYING: usize = QueryMask::Varying as usize;
+    const MATCH: usize = QueryMask::Match as usize;
+    let mut random = random::SplitMix64::new(seed);
+    let triple_index = INDEX_LOOKUP[mask];
+    let subindex = triple_index as usize;
+    let levels: Vec<usize> = (0..3).map(|level| (mask/[1, 3, 9][TRIPLE_PRIORITIZED[level][subindex]])%3).collect();
+    let population = estimate_triples(mask, triple);
+    let count = count.min(population);
+    if levels.contains(&VARYING) && count.saturating_mul(2) < population {
+        let result = NAMESPACE_INDEX.with(|namespace_index_cell| {
+            let namespace_index = namespace_index_cell.borrow();
+            let prefix = reorder_triple(&TRIPLE_PRIORITIZED, triple_index, &triple);
+            let fixed_alpha = if levels[0] == VARYING { None } else { get_symbol_handle(&namespace_index, prefix[0]) };
+            let mut namespaces: Vec<(&symbol::Identity, &NamespaceHandle)> = namespace_index.iter().collect();
+            namespaces.sort_unstable_by_key(|(namespace_identity, _namespace_handle)| **namespace_identity);
+            let upper_bound = namespaces.iter().map(|(_namespace_identity, namespace_handle)| namespace_handle.free_pool.upper_bound()).max().unwrap_or(0);
+            let beta_bound = BETA_SIZES.with(|beta_sizes| beta_sizes.borrow()[subindex].len().saturating_sub(1));
+            let gamma_bound = GAMMA_SIZES.with(|gamma_sizes| gamma_sizes.borrow()[subindex].len().saturating_sub(1));
+            if (fixed_alpha.is_none() && (levels[0] != VARYING || upper_bound == 0)) || beta_bound == 0 || gamma_bound == 0 {
+                return vec![];
+            }
+            let mut sorted_betas: HashMap<symbol::Symbol, Vec<symbol::Symbol>> = HashMap::new();
+            let mut sorted_gammas: HashMap<(symbol::Symbol, symbol::Symbol), Vec<symbol::Symbol>> = HashMap::new();
+            let mut chosen: HashSet<Triple> = HashSet::new();
+            let mut result: Vec<Triple> = vec![];
+            for _attempt in 0..count.saturating_mul(SAMPLING_ATTEMPTS_PER_ITEM) {
+                let (alpha, alpha_handle) = match fixed_alpha {
+                    Some(alpha_handle) => (prefix[0], alpha_handle),
+                    None => {
+                        let (namespace_identity, namespace_handle) = namespaces[random.below(namespaces.len())];
+                        let symbol_identity = random.below(upper_bound);
+                        match namespace_handle.symbol_index.get(&symbol_identity) {
+                            Some(symbol_handle) => (symbol::Symbol(*namespace_identity, symbol_identity), symbol_handle),
+                            None => { continue; }
                         }
                     }
+                };
+                let beta_self = &alpha_handle.subindices[subindex];
+                if beta_self.is_empty() {
+                    continue;
                 }
-            },
-            TripleQueryFunc::SearchVVV => {
-                for (namespace_identity, namespace_handle) in namespace_index.iter() {
-                    for (symbol_identity, symbol_handle) in namespace_handle.symbol_index.iter() {
-                        triple[0] = symbol::Symbol{0: *namespace_identity, 1: *symbol_identity};
-                        for (beta, gamma_self) in symbol_handle.subindices[triple_index as usize].iter() {
-                            triple[1] = *beta;
-                            for gamma in gamma_self.iter() {
-                                triple[2] = *gamma;
-                                result.push(triple);
-                            }
+                let beta = match levels[1] {
+                    MATCH => prefix[1],
+                    VARYING => {
+                        if levels[0] == VARYING && random.below(beta_bound) >= beta_self.len() {
+                            continue;
+                        }
+                        let betas = sorted_betas.entry(alpha).or_insert_with(|| sorted_keys(beta_self.keys(), None).collect());
+                        betas[random.below(betas.len())]
+                    },
+                    _ => prefix[1]
+                };
+                let gamma = match (levels[2], beta_self.get(&beta)) {
+                    (VARYING, Some(gamma_self)) => {
+                        if levels[1] == VARYING && random.below(gamma_bound) >= gamma_self.len() {
+                            continue;
                         }
+                        let gammas = sorted_gammas.entry((alpha, beta)).or_insert_with(|| sorted_keys(gamma_self.iter(), None).collect());
+                        gammas[random.below(gammas.len())]
+                    },
+                    (VARYING, None) => { continue; },
+                    _ => prefix[2]
+                };
+                let sampled_triple = reorder_triple(&TRIPLE_NORMALIZED, triple_index, &[alpha, beta, gamma]);
+                if chosen.insert(sampled_triple) {
+                    result.push(sampled_triple);
+                    if result.len() == count {
+                        break;
                     }
                 }
             }
+            result
+        });
+        if result.len() == count {
+            return result;
         }
-        result
-    })
+    }
+    sample_sorted(query_triples_with(mask, triple, |triples| triples.collect()), count, &mut random)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visited_triples() -> usize {
+        VISITED_TRIPLES.with(|visited_triples| visited_triples.replace(0))
+    }
+
+    fn setup() -> (Vec<symbol::Symbol>, Vec<symbol::Symbol>) {
+        manifest_symbol(symbol::Symbol(0, 0));
+        manifest_symbol(symbol::Symbol(0, 1));
+        manifest_symbol(symbol::Symbol(0, 2));
+        let common: Vec<symbol::Symbol> = (0..8).map(|_index| create_symbol(1)).collect();
+        let rare: Vec<symbol::Symbol> = (0..2).map(|_index| create_symbol(2)).collect();
+        for entity in &common {
+            for value in &common {
+                assert!(set_triple([*entity, common[0], *value], true));
+            }
+        }
+        assert!(set_triple([common[1], common[0], rare[0]], true));
+        assert!(set_triple([common[2], rare[1], rare[0]], true));
+        (common, rare)
+    }
+
+    #[test]
+    fn restricted_alpha_skips_other_namespaces() {
+        let (common, rare) = setup();
+        let restriction: NamespaceRestriction = [None, None, Some(HashSet::from([2]))];
+        visited_triples();
+        let mut result = query_triples_in_namespaces(13, [common[0]; 3], &restriction);
+        result.sort_unstable();
+        assert_eq!(result, vec![[common[1], common[0], rare[0]], [common[2], rare[1], rare[0]]]);
+        assert_eq!(visited_triples(), 2);
+    }
+
+    #[test]
+    fn average_matches_follow_histograms() {
+        setup();
+        assert_eq!(average_matches(12), 9);
+        assert_eq!(average_matches(9), 8);
+        assert!(set_triple([create_symbol(2), create_symbol(2), create_symbol(2)], true));
+        assert_eq!(average_matches(12), 8);
+    }
+
+    #[test]
+    fn restricted_beta_skips_other_namespaces() {
+        let (common, rare) = setup();
+        let restriction: NamespaceRestriction = [None, None, Some(HashSet::from([2]))];
+        visited_triples();
+        let result = query_triples_in_namespaces(12, [common[1], common[0], common[0]], &restriction);
+        assert_eq!(result, vec![[common[1], common[0], rare[0]]]);
+        assert_eq!(visited_triples(), 1);
+        let restriction: NamespaceRestriction = [None, Some(HashSet::from([2])), None];
+        let result = query_triples_in_namespaces(13, [common[0]; 3], &restriction);
+        assert_eq!(result, vec![[common[2], rare[1], rare[0]]]);
+        assert_eq!(visited_triples(), 1);
+    }
 }
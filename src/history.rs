@@ -0,0 +1,467 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use crate::bitops;
+use crate::symbol;
+use crate::graph;
+use crate::rope;
+
+pub enum Operation {
+    ManifestSymbol(symbol::Symbol),
+    ReleaseSymbol(symbol::Symbol),
+    SetTriple(graph::Triple, bool),
+    IncreaseLength(symbol::Symbol, usize, usize),
+    DecreaseLength(symbol::Symbol, usize, usize, Box<[usize]>),
+    WriteData(symbol::Symbol, usize, usize, Box<[usize]>, Box<[usize]>)
+}
+
+type Step = Vec<Operation>;
+
+struct History {
+    enabled: bool,
+    depth_limit: usize,
+    latest_version: usize,
+    base_version: usize,
+    pending: Step,
+    undo_stack: VecDeque<(usize, Step)>,
+    redo_stack: Vec<(usize, Step)>
+}
+
+impl History {
+    fn new() -> Self {
+        Self{enabled: false, depth_limit: 0, latest_version: 0, base_version: 0, pending: Vec::new(), undo_stack: VecDeque::new(), redo_stack: Vec::new()}
+    }
+
+    fn commit_step(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let step = std::mem::take(&mut self.pending);
+        self.latest_version += 1;
+        self.push_undo_step((self.latest_version, step));
+    }
+
+    fn push_undo_step(&mut self, step: (usize, Step)) {
+        self.undo_stack.push_back(step);
+        while self.undo_stack.len() > self.depth_limit {
+            self.base_version = self.undo_stack.pop_front().unwrap().0;
+        }
+    }
+
+    fn current_version(&self) -> usize {
+        match self.undo_stack.back() {
+            Some((version, _step)) => *version,
+            None => self.base_version
+        }
+    }
+
+    fn operations_since(&self, version: usize) -> Option<Vec<&Operation>> {
+        if !self.enabled {
+            return None;
+        }
+        let position = if version == self.base_version {
+            0
+        } else {
+            self.undo_stack.iter().position(|(step_version, _step)| *step_version == version)?+1
+        };
+        let mut result: Vec<&Operation> = vec![];
+        for (_version, step) in self.undo_stack.iter().skip(position) {
+            result.extend(step.iter());
+        }
+        result.extend(self.pending.iter());
+        Some(result)
+    }
+}
+
+thread_local!(static HISTORY: RefCell<History> = RefCell::new(History::new()));
+
+
+
+pub fn enable_history(depth_limit: usize) {
+    HISTORY.with(|history_cell| {
+        let mut history = history_cell.borrow_mut();
+        if !history.enabled {
+            history.latest_version += 1;
+            history.base_version = history.latest_version;
+        }
+        history.enabled = true;
+        history.depth_limit = depth_limit;
+        while history.undo_stack.len() > depth_limit {
+            history.base_version = history.undo_stack.pop_front().unwrap().0;
+        }
+    })
+}
+
+pub fn disable_history() {
+    HISTORY.with(|history_cell| {
+        let mut history = history_cell.borrow_mut();
+        let latest_version = history.latest_version;
+        *history = History::new();
+        history.latest_version = latest_version;
+    })
+}
+
+pub fn is_enabled() -> bool {
+    HISTORY.with(|history_cell| history_cell.borrow().enabled)
+}
+
+pub fn record(operation: Operation) {
+    HISTORY.with(|history_cell| {
+        let mut history = history_cell.borrow_mut();
+        if !history.enabled {
+            return;
+        }
+        history.pending.push(operation);
+        history.redo_stack.clear();
+    })
+}
+
+pub fn commit_step() {
+    HISTORY.with(|history_cell| {
+        history_cell.borrow_mut().commit_step();
+    })
+}
+
+pub fn current_version() -> usize {
+    HISTORY.with(|history_cell| history_cell.borrow().current_version())
+}
+
+pub fn can_undo() -> bool {
+    HISTORY.with(|history_cell| {
+        let history = history_cell.borrow();
+        !history.pending.is_empty() || !history.undo_stack.is_empty()
+    })
+}
+
+pub fn can_redo() -> bool {
+    HISTORY.with(|history_cell| {
+        let history = history_cell.borrow();
+        history.pending.is_empty() && !history.redo_stack.is_empty()
+    })
+}
+
+fn apply_step(step: &Step, reverse: bool) -> bool {
+    let operations: Vec<&Operation> = if reverse { step.iter().rev().collect() } else { step.iter().collect() };
+    for (index, operation) in operations.iter().enumerate() {
+        if !graph::apply_operation(operation, reverse) {
+            for applied in operations[..index].iter().rev() {
+                graph::apply_operation(applied, !reverse);
+            }
+            return false;
+        }
+    }
+    true
+}
+
+pub fn undo() -> bool {
+    let step = HISTORY.with(|history_cell| {
+        let mut history = history_cell.borrow_mut();
+        history.commit_step();
+        history.undo_stack.pop_back()
+    });
+    match step {
+        Some(step) => {
+            let result = apply_step(&step.1, true);
+            HISTORY.with(|history_cell| {
+                let mut history = history_cell.borrow_mut();
+                if result {
+                    history.redo_stack.push(step);
+                } else {
+                    history.undo_stack.push_back(step);
+                }
+            });
+            result
+        },
+        None => false
+    }
+}
+
+pub fn redo() -> bool {
+    let step = HISTORY.with(|history_cell| {
+        let mut history = history_cell.borrow_mut();
+        if !history.pending.is_empty() {
+            return None;
+        }
+        history.redo_stack.pop()
+    });
+    match step {
+        Some(step) => {
+            let result = apply_step(&step.1, false);
+            HISTORY.with(|history_cell| {
+                let mut history = history_cell.borrow_mut();
+                if result {
+                    history.push_undo_step(step);
+                } else {
+                    history.redo_stack.push(step);
+                }
+            });
+            result
+        },
+        None => false
+    }
+}
+
+
+
+pub struct HistoricalView {
+    version: usize
+}
+
+impl HistoricalView {
+    pub fn new(version: usize) -> Option<Self> {
+        let view = Self{version};
+        view.with_operations(|_operations| ())?;
+        Some(view)
+    }
+
+    fn with_operations<R, F: FnOnce(&[&Operation]) -> R>(&self, f: F) -> Option<R> {
+        HISTORY.with(|history_cell| {
+            let history = history_cell.borrow();
+            history.operations_since(self.version).map(|operations| f(&operations))
+        })
+    }
+
+    fn was_manifested(&self, operations: &[&Operation], symbol: symbol::Symbol) -> bool {
+        for operation in operations {
+            match operation {
+                Operation::ManifestSymbol(manifested) if *manifested == symbol => { return false; },
+                Operation::ReleaseSymbol(released) if *released == symbol => { return true; },
+                _ => {}
+            }
+        }
+        graph::is_manifested(symbol)
+    }
+
+    fn reconstruct_data(&self, operations: &[&Operation], symbol: symbol::Symbol) -> rope::BitRope {
+        let data_length = graph::get_length(symbol);
+        let mut data_content: Box<[usize]> = vec![0; data_length.div_ceil(bitops::ARCHITECTURE_SIZE)].into_boxed_slice();
+        graph::read_data(symbol, 0, data_length, &mut data_content);
+        let mut data_rope = rope::BitRope::new();
+        data_rope.insert_zeros(0, data_length);
+        data_rope.write(0, data_length, &data_content);
+        for operation in operations.iter().rev() {
+            match operation {
+                Operation::IncreaseLength(creased, offset, length) if *creased == symbol => {
+                    data_rope.remove(*offset, *length);
+                },
+                Operation::DecreaseLength(creased, offset, length, removed_data) if *creased == symbol => {
+                    data_rope.insert_zeros(*offset, *length);
+                    data_rope.write(*offset, *length, removed_data);
+                },
+                Operation::WriteData(written, offset, length, previous_data, _written_data) if *written == symbol => {
+                    data_rope.write(*offset, *length, previous_data);
+                },
+                _ => {}
+            }
+        }
+        data_rope
+    }
+
+    pub fn query_symbols(&self, namespace_identity: symbol::Identity) -> Option<Vec<symbol::Identity>> {
+        self.with_operations(|operations| {
+            let mut result = graph::query_symbols(namespace_identity);
+            let mut changed: HashMap<symbol::Identity, bool> = HashMap::new();
+            for operation in operations {
+                match operation {
+                    Operation::ManifestSymbol(symbol) if symbol.0 == namespace_identity => { changed.entry(symbol.1).or_insert(false); },
+                    Operation::ReleaseSymbol(symbol) if symbol.0 == namespace_identity => { changed.entry(symbol.1).or_insert(true); },
+                    _ => {}
+                }
+            }
+            result.retain(|symbol_identity| !changed.contains_key(symbol_identity));
+            for (symbol_identity, manifested) in changed {
+                if manifested {
+                    result.push(symbol_identity);
+                }
+            }
+            if graph::ordered_results() {
+                result.sort_unstable();
+            }
+            result
+        })
+    }
+
+    pub fn query_triples(&self, mask: usize, triple: graph::Triple) -> Option<Vec<graph::Triple>> {
+        self.with_operations(|operations| {
+            let mut varying_mask: usize = 0;
+            let mut ignored = [false; 3];
+            for triple_index in (0..3).rev() {
+                let position_mask = (mask/[1, 3, 9][triple_index])%3;
+                ignored[triple_index] = position_mask == 2;
+                varying_mask = varying_mask*3+if ignored[triple_index] { 1 } else { position_mask };
+            }
+            let mut changed: HashMap<graph::Triple, bool> = HashMap::new();
+            for operation in operations {
+                if let Operation::SetTriple(changed_triple, linked) = operation {
+                    changed.entry(*changed_triple).or_insert(!linked);
+                }
+            }
+            let mut matches = graph::query_triples(varying_mask, triple);
+            matches.retain(|matched_triple| !changed.contains_key(matched_triple));
+            for (changed_triple, linked) in changed {
+                if linked && (0..3).all(|triple_index| (varying_mask/[1, 3, 9][triple_index])%3 != 0 || changed_triple[triple_index] == triple[triple_index]) {
+                    matches.push(changed_triple);
+                }
+            }
+            let mut result: Vec<graph::Triple> = vec![];
+            let mut projected: HashSet<graph::Triple> = HashSet::new();
+            for mut matched_triple in matches {
+                for triple_index in 0..3 {
+                    if ignored[triple_index] {
+                        matched_triple[triple_index] = triple[triple_index];
+                    }
+                }
+                if projected.insert(matched_triple) {
+                    result.push(matched_triple);
+                }
+            }
+            if graph::ordered_results() {
+                result.sort_unstable();
+            }
+            result
+        })
+    }
+
+    pub fn get_length(&self, symbol: symbol::Symbol) -> Option<usize> {
+        self.with_operations(|operations| {
+            if !self.was_manifested(operations, symbol) {
+                return 0;
+            }
+            self.reconstruct_data(operations, symbol).len()
+        })
+    }
+
+    pub fn read_data(&self, symbol: symbol::Symbol, offset: usize, length: usize, dst: &mut [usize]) -> bool {
+        self.with_operations(|operations| {
+            if !self.was_manifested(operations, symbol) {
+                return false;
+            }
+            let data_rope = self.reconstruct_data(operations, symbol);
+            if offset+length > data_rope.len() {
+                return false;
+            }
+            let data_content = data_rope.read(offset, length);
+            dst[..data_content.len()].copy_from_slice(&data_content);
+            true
+        }).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> symbol::Symbol {
+        graph::manifest_symbol(symbol::Symbol(0, 0));
+        graph::manifest_symbol(symbol::Symbol(0, 1));
+        enable_history(16);
+        graph::create_symbol(1)
+    }
+
+    fn read(symbol: symbol::Symbol) -> Vec<usize> {
+        let length = graph::get_length(symbol);
+        let mut data = vec![0; length.div_ceil(bitops::ARCHITECTURE_SIZE)];
+        assert!(graph::read_data(symbol, 0, length, &mut data));
+        data
+    }
+
+    #[test]
+    fn manifest_and_release_round_trip() {
+        let a = setup();
+        commit_step();
+        let b = graph::create_symbol(1);
+        commit_step();
+        assert!(graph::release_symbol(b));
+        commit_step();
+        assert!(undo());
+        assert!(graph::is_manifested(b));
+        assert!(undo());
+        assert!(graph::is_manifested(a) && !graph::is_manifested(b));
+        assert!(undo());
+        assert!(!graph::is_manifested(a));
+        assert!(!undo());
+        assert!(redo());
+        assert!(redo());
+        assert!(graph::is_manifested(a) && graph::is_manifested(b));
+        assert!(redo());
+        assert!(!graph::is_manifested(b));
+        assert!(!redo());
+    }
+
+    #[test]
+    fn link_round_trip() {
+        let a = setup();
+        let b = graph::create_symbol(1);
+        commit_step();
+        let triple = [a, b, a];
+        assert!(graph::set_triple(triple, true));
+        commit_step();
+        assert!(graph::set_triple(triple, false));
+        commit_step();
+        assert!(undo());
+        assert_eq!(graph::query_triples(0, triple), vec![triple]);
+        assert!(undo());
+        assert!(graph::query_triples(0, triple).is_empty());
+        assert!(redo());
+        assert!(redo());
+        assert!(graph::query_triples(0, triple).is_empty());
+    }
+
+    #[test]
+    fn crease_and_write_round_trip() {
+        let a = setup();
+        assert!(graph::crease_length(a, 0, 96));
+        assert!(graph::write_data(a, 0, 96, &[0x0123456789abcdef, 0x76543210]));
+        commit_step();
+        let written = read(a);
+        assert!(graph::crease_length(a, 8, -24));
+        assert!(graph::write_data(a, 0, 8, &[0xff]));
+        assert!(graph::crease_length(a, 72, 16));
+        commit_step();
+        let edited = read(a);
+        assert!(undo());
+        assert_eq!(read(a), written);
+        assert!(redo());
+        assert_eq!(read(a), edited);
+        assert!(undo());
+        assert!(undo());
+        assert_eq!(graph::get_length(a), 0);
+        assert!(redo());
+        assert_eq!(read(a), written);
+    }
+
+    #[test]
+    fn failed_undo_leaves_state_intact() {
+        let a = setup();
+        commit_step();
+        assert!(graph::crease_length(a, 0, 8));
+        let b = graph::create_symbol(1);
+        commit_step();
+        assert!(graph::apply_operation(&Operation::IncreaseLength(a, 0, 8), true));
+        assert!(!undo());
+        assert!(graph::is_manifested(b));
+        assert_eq!(graph::get_length(a), 0);
+        assert!(can_undo());
+        assert!(graph::apply_operation(&Operation::IncreaseLength(a, 0, 8), false));
+        assert!(undo());
+        assert!(!graph::is_manifested(b));
+        assert!(redo());
+        assert!(graph::is_manifested(b));
+        assert_eq!(graph::get_length(a), 8);
+    }
+
+    #[test]
+    fn current_version_does_not_commit() {
+        let a = setup();
+        commit_step();
+        let version = current_version();
+        assert!(graph::crease_length(a, 0, 8));
+        assert_eq!(current_version(), version);
+        assert_eq!(HistoricalView::new(version).unwrap().get_length(a), Some(0));
+        commit_step();
+        assert!(current_version() > version);
+        assert!(undo());
+        assert_eq!(current_version(), version);
+    }
+}
@@ -3,6 +3,19 @@ use std::cell::RefCell;
 mod bitops;
 mod symbol;
 mod graph;
+mod aggregate;
+mod content;
+mod history;
+mod query;
+mod random;
+mod range;
+mod rope;
+mod rules;
+mod sparql;
+mod subgraph;
+mod text;
+mod traversal;
+mod views;
 use wasm_bindgen::prelude::*;
 
 unsafe fn transmute_vec<S, T>(mut vec: Vec<S>) -> Vec<T> {
@@ -15,6 +28,11 @@ unsafe fn transmute_vec<S, T>(mut vec: Vec<S>) -> Vec<T> {
 
 
 
+const VARIABLE_NAMESPACE_IDENTITY: symbol::Identity = usize::MAX;
+const GROUP_NAMESPACE_IDENTITY: symbol::Identity = usize::MAX-1;
+const OPTIONAL_GROUP: symbol::Identity = 0;
+const NOT_EXISTS_GROUP: symbol::Identity = 1;
+
 thread_local!(static IDENTITY_POOL: RefCell<symbol::IdentityPool> = RefCell::new(symbol::IdentityPool::new()));
 
 #[wasm_bindgen]
@@ -70,6 +88,11 @@ pub fn setTriple(entity_namespace_identity: symbol::Identity, entity_symbol_iden
     ], linked)
 }
 
+#[wasm_bindgen]
+pub fn setOrderedResults(ordered: bool) {
+    graph::set_ordered_results(ordered)
+}
+
 #[wasm_bindgen]
 pub fn querySymbols(namespaceIdentity: symbol::Identity) -> Vec<symbol::Identity> {
     graph::query_symbols(namespaceIdentity)
@@ -88,6 +111,419 @@ pub fn queryTriples(mask: usize,
     unsafe { transmute_vec::<graph::Triple, symbol::Identity>(result) }
 }
 
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn queryTriplesInNamespaces(mask: usize,
+                                entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+                                attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+                                value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity,
+                                entity_namespaces: &[symbol::Identity], attribute_namespaces: &[symbol::Identity], value_namespaces: &[symbol::Identity]) -> Vec<symbol::Identity> {
+    let restriction = |namespaces: &[symbol::Identity]| if namespaces.is_empty() { None } else { Some(namespaces.iter().cloned().collect()) };
+    let result = graph::query_triples_in_namespaces(mask, [
+        symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        symbol::Symbol(value_namespace_identity, value_symbol_identity)
+    ], &[restriction(entity_namespaces), restriction(attribute_namespaces), restriction(value_namespaces)]);
+    unsafe { transmute_vec::<graph::Triple, symbol::Identity>(result) }
+}
+
+#[wasm_bindgen]
+pub fn sampleSymbols(namespace_identity: symbol::Identity, count: usize, seed: u64) -> Vec<symbol::Identity> {
+    graph::sample_symbols(namespace_identity, count, seed).iter().map(|symbol| symbol.1).collect()
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn sampleTriples(mask: usize,
+                     entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+                     attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+                     value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity,
+                     count: usize, seed: u64) -> Vec<symbol::Identity> {
+    let result = graph::sample_triples(mask, [
+        symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        symbol::Symbol(value_namespace_identity, value_symbol_identity)
+    ], count, seed);
+    unsafe { transmute_vec::<graph::Triple, symbol::Identity>(result) }
+}
+
+#[wasm_bindgen]
+pub fn countTriples(mask: usize,
+                    entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+                    attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+                    value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity) -> usize {
+    graph::count_triples(mask, [
+        symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        symbol::Symbol(value_namespace_identity, value_symbol_identity)
+    ])
+}
+
+#[wasm_bindgen]
+pub fn symbolStatistics(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity) -> Option<Vec<usize>> {
+    let statistics = graph::symbol_statistics(symbol::Symbol(namespace_identity, symbol_identity))?;
+    let mut result: Vec<usize> = vec![];
+    result.extend_from_slice(&statistics.triple_counts);
+    result.extend_from_slice(&statistics.beta_counts);
+    result.push(statistics.data_length);
+    Some(result)
+}
+
+#[wasm_bindgen]
+pub fn namespaceStatistics(namespace_identity: symbol::Identity) -> Option<Vec<usize>> {
+    let statistics = graph::namespace_statistics(namespace_identity)?;
+    let mut result: Vec<usize> = vec![statistics.symbol_count];
+    result.extend_from_slice(&statistics.triple_counts);
+    result.push(statistics.data_length);
+    result.push(statistics.max_degree);
+    Some(result)
+}
+
+#[wasm_bindgen]
+pub fn topSymbolsByDegree(namespace_identity: Option<symbol::Identity>, count: usize) -> Vec<usize> {
+    let mut result: Vec<usize> = vec![];
+    for (symbol, degree) in graph::top_symbols_by_degree(namespace_identity, count) {
+        result.push(symbol.0);
+        result.push(symbol.1);
+        result.push(degree);
+    }
+    result
+}
+
+fn decode_pattern(pattern: &[symbol::Identity], variables: &mut Vec<symbol::Identity>) -> query::Pattern {
+    let mut term = |namespace_identity: symbol::Identity, symbol_identity: symbol::Identity| {
+        if namespace_identity == VARIABLE_NAMESPACE_IDENTITY {
+            if let Err(index) = variables.binary_search(&symbol_identity) {
+                variables.insert(index, symbol_identity);
+            }
+            query::Term::Variable(symbol_identity.to_string())
+        } else {
+            query::Term::Constant(symbol::Symbol(namespace_identity, symbol_identity))
+        }
+    };
+    [term(pattern[0], pattern[1]), term(pattern[2], pattern[3]), term(pattern[4], pattern[5])]
+}
+
+fn decode_patterns(terms: &[symbol::Identity]) -> Option<(Vec<query::Pattern>, Vec<symbol::Identity>)> {
+    if terms.len()%6 != 0 {
+        return None;
+    }
+    let mut variables: Vec<symbol::Identity> = vec![];
+    let patterns: Vec<query::Pattern> = terms.chunks(6).map(|pattern| decode_pattern(pattern, &mut variables)).collect();
+    Some((patterns, variables))
+}
+
+fn close_group(stack: &mut Vec<(symbol::Identity, Vec<query::Element>)>) {
+    let (kind, elements) = stack.pop().unwrap();
+    let element = if kind == OPTIONAL_GROUP { query::Element::Optional(elements) } else { query::Element::NotExists(elements) };
+    stack.last_mut().unwrap().1.push(element);
+}
+
+fn decode_elements(terms: &[symbol::Identity]) -> Option<(Vec<query::Element>, Vec<symbol::Identity>)> {
+    if terms.len()%6 != 0 {
+        return None;
+    }
+    let mut variables: Vec<symbol::Identity> = vec![];
+    let mut stack: Vec<(symbol::Identity, Vec<query::Element>)> = vec![(GROUP_NAMESPACE_IDENTITY, vec![])];
+    for pattern in terms.chunks(6) {
+        if pattern[0] != GROUP_NAMESPACE_IDENTITY {
+            let pattern = decode_pattern(pattern, &mut variables);
+            stack.last_mut().unwrap().1.push(query::Element::Pattern(pattern));
+        } else if pattern[1] == OPTIONAL_GROUP || pattern[1] == NOT_EXISTS_GROUP {
+            stack.push((pattern[1], vec![]));
+        } else if stack.len() > 1 {
+            close_group(&mut stack);
+        } else {
+            return None;
+        }
+    }
+    if stack.len() > 1 {
+        return None;
+    }
+    Some((stack.pop().unwrap().1, variables))
+}
+
+fn encode_bindings(bindings: &query::Bindings, variables: &[symbol::Identity]) -> Vec<symbol::Identity> {
+    let columns: Vec<Option<usize>> = variables.iter().map(|variable| bindings.column(&variable.to_string())).collect();
+    let mut result: Vec<symbol::Identity> = vec![];
+    for row in &bindings.rows {
+        for column in &columns {
+            let symbol = match column.and_then(|column| row[column]) {
+                Some(symbol) => symbol,
+                None => symbol::Symbol(VARIABLE_NAMESPACE_IDENTITY, VARIABLE_NAMESPACE_IDENTITY)
+            };
+            result.push(symbol.0);
+            result.push(symbol.1);
+        }
+    }
+    result
+}
+
+#[wasm_bindgen]
+pub fn queryPatterns(terms: &[symbol::Identity]) -> Option<Vec<symbol::Identity>> {
+    let (elements, variables) = decode_elements(terms)?;
+    Some(encode_bindings(&query::Query::from_elements(&elements).evaluate(), &variables))
+}
+
+#[wasm_bindgen]
+pub fn explainPatterns(terms: &[symbol::Identity]) -> Option<String> {
+    Some(query::Query::from_elements(&decode_elements(terms)?.0).explain())
+}
+
+#[wasm_bindgen]
+pub struct TextQueryResult {
+    variables: Vec<String>,
+    rows: Vec<symbol::Identity>
+}
+
+#[wasm_bindgen]
+impl TextQueryResult {
+    #[wasm_bindgen(getter)]
+    pub fn variables(&self) -> Vec<String> {
+        self.variables.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn rows(&self) -> Vec<symbol::Identity> {
+        self.rows.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn queryText(text: &str) -> Result<TextQueryResult, JsValue> {
+    let select_query = sparql::parse(text).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let bindings = select_query.evaluate();
+    let mut rows: Vec<symbol::Identity> = vec![];
+    for row in &bindings.rows {
+        for binding in row {
+            let symbol = binding.unwrap_or(symbol::Symbol(VARIABLE_NAMESPACE_IDENTITY, VARIABLE_NAMESPACE_IDENTITY));
+            rows.push(symbol.0);
+            rows.push(symbol.1);
+        }
+    }
+    Ok(TextQueryResult{variables: bindings.variables, rows})
+}
+
+#[wasm_bindgen]
+pub struct TripleCursor {
+    mask: usize,
+    triple: graph::Triple,
+    generation: usize,
+    key: Option<Vec<usize>>
+}
+
+#[wasm_bindgen]
+impl TripleCursor {
+    #[wasm_bindgen(constructor)]
+    pub fn new(mask: usize,
+               entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+               attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+               value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity) -> TripleCursor {
+        TripleCursor{
+            mask,
+            triple: [
+                symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+                symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+                symbol::Symbol(value_namespace_identity, value_symbol_identity)
+            ],
+            generation: graph::generation(),
+            key: Some(vec![])
+        }
+    }
+
+    pub fn next(&mut self, chunk_size: usize) -> Option<Vec<symbol::Identity>> {
+        if self.generation != graph::generation() {
+            return None;
+        }
+        let (result, key) = match &self.key {
+            Some(key) => graph::query_triples_after(self.mask, self.triple, key, chunk_size)?,
+            None => (vec![], None)
+        };
+        self.key = key;
+        Some(unsafe { transmute_vec::<graph::Triple, symbol::Identity>(result) })
+    }
+}
+
+#[wasm_bindgen]
+pub struct QueryPage {
+    items: Vec<symbol::Identity>,
+    token: Option<String>
+}
+
+impl QueryPage {
+    fn new<T>(page: Option<(Vec<T>, Option<graph::ContinuationToken>)>) -> Option<QueryPage> {
+        let (items, token) = page?;
+        Some(QueryPage{
+            items: unsafe { transmute_vec::<T, symbol::Identity>(items) },
+            token: token.map(|token| token.encode())
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl QueryPage {
+    #[wasm_bindgen(getter)]
+    pub fn items(&self) -> Vec<symbol::Identity> {
+        self.items.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn token(&self) -> Option<String> {
+        self.token.clone()
+    }
+}
+
+fn decode_token(token: Option<String>) -> Option<graph::ContinuationToken> {
+    match token {
+        Some(token) => graph::ContinuationToken::decode(&token),
+        None => Some(graph::ContinuationToken::new())
+    }
+}
+
+#[wasm_bindgen]
+pub fn querySymbolsPage(namespace_identity: symbol::Identity, token: Option<String>, limit: usize) -> Option<QueryPage> {
+    QueryPage::new(graph::query_symbols_page(namespace_identity, decode_token(token)?, limit))
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn queryTriplesPage(mask: usize,
+                        entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+                        attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+                        value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity,
+                        token: Option<String>, limit: usize) -> Option<QueryPage> {
+    QueryPage::new(graph::query_triples_page(mask, [
+        symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        symbol::Symbol(value_namespace_identity, value_symbol_identity)
+    ], decode_token(token)?, limit))
+}
+
+fn encode_reachable(reachable: Vec<(symbol::Symbol, usize)>) -> Vec<usize> {
+    let mut result: Vec<usize> = vec![];
+    for (symbol, distance) in reachable {
+        result.push(symbol.0);
+        result.push(symbol.1);
+        result.push(distance);
+    }
+    result
+}
+
+#[wasm_bindgen]
+pub fn matchSubgraph(terms: &[symbol::Identity], injective: bool) -> Option<Vec<symbol::Identity>> {
+    let (edges, variables) = decode_patterns(terms)?;
+    Some(encode_bindings(&subgraph::find_matches(&edges, injective), &variables))
+}
+
+#[wasm_bindgen]
+pub fn transitiveClosure(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity,
+                         attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity, forward: bool) -> Vec<usize> {
+    encode_reachable(traversal::transitive_closure(
+        symbol::Symbol(namespace_identity, symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        if forward { traversal::Direction::Forward } else { traversal::Direction::Backward }
+    ))
+}
+
+#[wasm_bindgen]
+pub fn reachableWithin(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity,
+                       attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity, forward: bool, max_depth: usize) -> Vec<usize> {
+    encode_reachable(traversal::reachable(
+        symbol::Symbol(namespace_identity, symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        if forward { traversal::Direction::Forward } else { traversal::Direction::Backward },
+        max_depth
+    ))
+}
+
+#[wasm_bindgen]
+pub fn shortestPath(source_namespace_identity: symbol::Identity, source_symbol_identity: symbol::Identity,
+                    target_namespace_identity: symbol::Identity, target_symbol_identity: symbol::Identity,
+                    attributes: &[symbol::Identity], namespaces: &[symbol::Identity], max_depth: usize) -> Option<Vec<symbol::Identity>> {
+    if attributes.len()%2 != 0 {
+        return None;
+    }
+    let mut filter = traversal::PathFilter::new();
+    if !attributes.is_empty() {
+        filter.attributes = Some(attributes.chunks(2).map(|attribute| symbol::Symbol(attribute[0], attribute[1])).collect());
+    }
+    if !namespaces.is_empty() {
+        filter.namespaces = Some(namespaces.iter().cloned().collect());
+    }
+    filter.max_depth = max_depth;
+    let result = traversal::shortest_path(
+        symbol::Symbol(source_namespace_identity, source_symbol_identity),
+        symbol::Symbol(target_namespace_identity, target_symbol_identity),
+        &filter
+    )?;
+    Some(unsafe { transmute_vec::<graph::Triple, symbol::Identity>(result) })
+}
+
+#[wasm_bindgen]
+pub fn registerView(terms: &[symbol::Identity]) -> Option<usize> {
+    Some(views::register_view(&decode_patterns(terms)?.0))
+}
+
+#[wasm_bindgen]
+pub fn unregisterView(view: usize) -> bool {
+    views::unregister_view(view)
+}
+
+#[wasm_bindgen]
+pub fn readView(view: usize) -> Option<Vec<symbol::Identity>> {
+    let bindings = views::read_view(view)?;
+    let mut variables: Vec<symbol::Identity> = bindings.variables.iter().filter_map(|name| name.parse::<symbol::Identity>().ok()).collect();
+    variables.sort_unstable();
+    Some(encode_bindings(&bindings, &variables))
+}
+
+#[wasm_bindgen]
+pub fn countView(view: usize) -> Option<usize> {
+    views::count_view(view)
+}
+
+#[wasm_bindgen]
+pub fn addRule(terms: &[symbol::Identity]) -> bool {
+    let mut patterns = match decode_patterns(terms) {
+        Some((patterns, _variables)) if !patterns.is_empty() => patterns,
+        _ => { return false; }
+    };
+    let head = patterns.remove(0);
+    rules::add_rule(rules::Rule{head, body: patterns})
+}
+
+#[wasm_bindgen]
+pub fn clearRules() {
+    rules::clear_rules()
+}
+
+#[wasm_bindgen]
+pub fn materializeRules() -> usize {
+    rules::materialize()
+}
+
+#[wasm_bindgen]
+pub fn retractDerived() -> usize {
+    rules::retract_derived()
+}
+
+#[wasm_bindgen]
+pub fn isDerived(entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+                 attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+                 value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity) -> bool {
+    rules::is_derived([
+        symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        symbol::Symbol(value_namespace_identity, value_symbol_identity)
+    ])
+}
+
+#[wasm_bindgen]
+pub fn queryPatternsWithRules(terms: &[symbol::Identity]) -> Option<Vec<symbol::Identity>> {
+    let (patterns, variables) = decode_patterns(terms)?;
+    Some(encode_bindings(&rules::query(&patterns), &variables))
+}
+
 #[wasm_bindgen]
 pub fn getLength(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity) -> usize {
     graph::get_length(symbol::Symbol(namespace_identity, symbol_identity))
@@ -99,6 +535,22 @@ pub fn creaseLength(namespace_identity: symbol::Identity, symbol_identity: symbo
 }
 
 #[wasm_bindgen]
+pub fn getCapacity(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity) -> usize {
+    graph::get_capacity(symbol::Symbol(namespace_identity, symbol_identity))
+}
+
+#[wasm_bindgen]
+pub fn reserveCapacity(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity, additional: usize) -> bool {
+    graph::reserve_capacity(symbol::Symbol(namespace_identity, symbol_identity), additional)
+}
+
+#[wasm_bindgen]
+pub fn shrinkCapacityToFit(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity) -> bool {
+    graph::shrink_capacity_to_fit(symbol::Symbol(namespace_identity, symbol_identity))
+}
+
+#[wasm_bindgen]
+#[allow(clippy::manual_div_ceil)]
 pub fn readData(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity, offset: usize, length: usize) -> Option<Vec<u8>> {
     let mut dst: Vec<usize> = vec![0; (length+bitops::ARCHITECTURE_SIZE-1)/bitops::ARCHITECTURE_SIZE];
     if graph::read_data(symbol::Symbol(namespace_identity, symbol_identity), offset, length, &mut dst) {
@@ -123,3 +575,236 @@ pub fn replaceData(dst_namespace_identity: symbol::Identity, dst_symbol_identity
         length
     )
 }
+
+
+
+#[wasm_bindgen]
+pub fn enableHistory(depth_limit: usize) {
+    history::enable_history(depth_limit)
+}
+
+#[wasm_bindgen]
+pub fn disableHistory() {
+    history::disable_history()
+}
+
+#[wasm_bindgen]
+pub fn commitStep() {
+    history::commit_step()
+}
+
+#[wasm_bindgen]
+pub fn undo() -> bool {
+    history::undo()
+}
+
+#[wasm_bindgen]
+pub fn redo() -> bool {
+    history::redo()
+}
+
+#[wasm_bindgen]
+pub fn canUndo() -> bool {
+    history::can_undo()
+}
+
+#[wasm_bindgen]
+pub fn canRedo() -> bool {
+    history::can_redo()
+}
+
+#[wasm_bindgen]
+pub fn currentVersion() -> usize {
+    history::current_version()
+}
+
+#[wasm_bindgen]
+pub fn querySymbolsAt(version: usize, namespace_identity: symbol::Identity) -> Option<Vec<symbol::Identity>> {
+    history::HistoricalView::new(version)?.query_symbols(namespace_identity)
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn queryTriplesAt(version: usize, mask: usize,
+                      entity_namespace_identity: symbol::Identity, entity_symbol_identity: symbol::Identity,
+                      attribute_namespace_identity: symbol::Identity, attribute_symbol_identity: symbol::Identity,
+                      value_namespace_identity: symbol::Identity, value_symbol_identity: symbol::Identity) -> Option<Vec<symbol::Identity>> {
+    let result = history::HistoricalView::new(version)?.query_triples(mask, [
+        symbol::Symbol(entity_namespace_identity, entity_symbol_identity),
+        symbol::Symbol(attribute_namespace_identity, attribute_symbol_identity),
+        symbol::Symbol(value_namespace_identity, value_symbol_identity)
+    ])?;
+    Some(unsafe { transmute_vec::<graph::Triple, symbol::Identity>(result) })
+}
+
+#[wasm_bindgen]
+pub fn getLengthAt(version: usize, namespace_identity: symbol::Identity, symbol_identity: symbol::Identity) -> Option<usize> {
+    history::HistoricalView::new(version)?.get_length(symbol::Symbol(namespace_identity, symbol_identity))
+}
+
+#[wasm_bindgen]
+pub fn readDataAt(version: usize, namespace_identity: symbol::Identity, symbol_identity: symbol::Identity, offset: usize, length: usize) -> Option<Vec<u8>> {
+    let mut dst: Vec<usize> = vec![0; length.div_ceil(bitops::ARCHITECTURE_SIZE)];
+    if history::HistoricalView::new(version)?.read_data(symbol::Symbol(namespace_identity, symbol_identity), offset, length, &mut dst) {
+        Some(unsafe { transmute_vec::<usize, u8>(dst) })
+    } else {
+        None
+    }
+}
+
+#[wasm_bindgen]
+pub fn findInData(namespace_identity: symbol::Identity, symbol_identity: symbol::Identity, pattern_length: usize, pattern: &[usize], start_offset: usize, byte_aligned: bool) -> Option<usize> {
+    if pattern.len()*bitops::ARCHITECTURE_SIZE < pattern_length {
+        return None;
+    }
+    let symbol = symbol::Symbol(namespace_identity, symbol_identity);
+    if byte_aligned {
+        graph::find_in_data_byte_aligned(symbol, pattern, pattern_length, start_offset)
+    } else {
+        graph::find_in_data(symbol, pattern, pattern_length, start_offset)
+    }
+}
+
+#[wasm_bindgen]
+pub fn findInNamespace(namespace_identity: symbol::Identity, pattern_length: usize, pattern: &[usize], byte_aligned: bool) -> Vec<usize> {
+    if pattern.len()*bitops::ARCHITECTURE_SIZE < pattern_length {
+        return vec![];
+    }
+    let mut result: Vec<usize> = vec![];
+    for (symbol, offset) in graph::find_in_namespace(namespace_identity, pattern, pattern_length, byte_aligned) {
+        result.push(symbol.1);
+        result.push(offset);
+    }
+    result
+}
+
+#[wasm_bindgen]
+pub fn enableContentIndex() {
+    content::enable_content_index()
+}
+
+#[wasm_bindgen]
+pub fn disableContentIndex() {
+    content::disable_content_index()
+}
+
+#[wasm_bindgen]
+pub fn findSymbolsByContent(data_length: usize, data: &[usize]) -> Option<Vec<symbol::Identity>> {
+    if data.len()*bitops::ARCHITECTURE_SIZE < data_length {
+        return None;
+    }
+    let result = content::find_symbols_by_content(data, data_length)?;
+    Some(unsafe { transmute_vec::<symbol::Symbol, symbol::Identity>(result) })
+}
+
+#[wasm_bindgen]
+pub fn createRangeIndex(namespace_identity: symbol::Identity, encoding: u8) -> bool {
+    let encoding = match encoding {
+        0 => range::Encoding::Unsigned,
+        1 => range::Encoding::Signed,
+        2 => range::Encoding::Float,
+        _ => { return false; }
+    };
+    range::create_range_index(namespace_identity, encoding)
+}
+
+#[wasm_bindgen]
+pub fn dropRangeIndex(namespace_identity: symbol::Identity) -> bool {
+    range::drop_range_index(namespace_identity)
+}
+
+fn encode_range(symbols: Option<Vec<symbol::Symbol>>) -> Option<Vec<symbol::Identity>> {
+    Some(symbols?.iter().map(|symbol| symbol.1).collect())
+}
+
+#[wasm_bindgen]
+pub fn queryRangeUnsigned(namespace_identity: symbol::Identity, min: u64, max: u64) -> Option<Vec<symbol::Identity>> {
+    encode_range(range::query_range_unsigned(namespace_identity, min, max))
+}
+
+#[wasm_bindgen]
+pub fn queryRangeSigned(namespace_identity: symbol::Identity, min: i64, max: i64) -> Option<Vec<symbol::Identity>> {
+    encode_range(range::query_range_signed(namespace_identity, min, max))
+}
+
+#[wasm_bindgen]
+pub fn queryRangeFloat(namespace_identity: symbol::Identity, min: f64, max: f64) -> Option<Vec<symbol::Identity>> {
+    encode_range(range::query_range_float(namespace_identity, min, max))
+}
+
+#[wasm_bindgen]
+pub fn createTextIndex(namespace_identity: symbol::Identity) -> bool {
+    text::create_text_index(namespace_identity)
+}
+
+#[wasm_bindgen]
+pub fn dropTextIndex(namespace_identity: symbol::Identity) -> bool {
+    text::drop_text_index(namespace_identity)
+}
+
+#[wasm_bindgen]
+pub fn searchText(namespace_identity: symbol::Identity, query: &str, limit: usize) -> Option<Vec<symbol::Identity>> {
+    Some(text::search_text(namespace_identity, query)?.iter().take(limit).map(|(symbol, _score)| symbol.1).collect())
+}
+
+#[wasm_bindgen]
+pub struct AggregateResult {
+    keys: Vec<symbol::Identity>,
+    values: Vec<f64>
+}
+
+#[wasm_bindgen]
+impl AggregateResult {
+    #[wasm_bindgen(getter)]
+    pub fn keys(&self) -> Vec<symbol::Identity> {
+        self.keys.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn values(&self) -> Vec<f64> {
+        self.values.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn aggregatePatterns(terms: &[symbol::Identity], group_by: &[symbol::Identity], operators: &[usize]) -> Option<AggregateResult> {
+    let group_by: Vec<String> = group_by.iter().map(|variable| variable.to_string()).collect();
+    let mut aggregates: Vec<aggregate::Aggregate> = vec![];
+    for operator in operators.chunks(3) {
+        if operator.len() < 3 {
+            return None;
+        }
+        let encoding = match operator[2] {
+            0 => range::Encoding::Unsigned,
+            1 => range::Encoding::Signed,
+            2 => range::Encoding::Float,
+            _ => { return None; }
+        };
+        aggregates.push(match operator[0] {
+            0 => aggregate::Aggregate::Count,
+            1 => aggregate::Aggregate::CountDistinct(operator[1].to_string()),
+            2 => aggregate::Aggregate::Min(operator[1].to_string(), encoding),
+            3 => aggregate::Aggregate::Max(operator[1].to_string(), encoding),
+            _ => { return None; }
+        });
+    }
+    let (elements, _variables) = decode_elements(terms)?;
+    let mut result = AggregateResult{keys: vec![], values: vec![]};
+    for row in aggregate::aggregate(&elements, &group_by, &aggregates) {
+        for binding in row.key {
+            let symbol = binding.unwrap_or(symbol::Symbol(VARIABLE_NAMESPACE_IDENTITY, VARIABLE_NAMESPACE_IDENTITY));
+            result.keys.push(symbol.0);
+            result.keys.push(symbol.1);
+        }
+        for value in row.values {
+            result.values.push(match value {
+                aggregate::Value::Count(count) => count as f64,
+                aggregate::Value::Unsigned(value) => value as f64,
+                aggregate::Value::Signed(value) => value as f64,
+                aggregate::Value::Float(value) => value,
+                aggregate::Value::Missing => f64::NAN
+            });
+        }
+    }
+    Some(result)
+}
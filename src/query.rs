@@ -0,0 +1,460 @@
+use std::collections::HashSet;
+use crate::symbol;
+use crate::graph;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Term {
+    Constant(symbol::Symbol),
+    Variable(String)
+}
+
+pub type Pattern = [Term; 3];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Slot {
+    Constant(symbol::Symbol),
+    Variable(usize)
+}
+
+pub enum Expression {
+    Equal(Term, Term),
+    NotEqual(Term, Term),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Exists(Vec<Element>)
+}
+
+pub enum Element {
+    Pattern(Pattern),
+    Optional(Vec<Element>),
+    NotExists(Vec<Element>),
+    Filter(Expression)
+}
+
+enum Condition {
+    Equal(Slot, Slot),
+    NotEqual(Slot, Slot),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Exists(Group)
+}
+
+type Row = Vec<Option<symbol::Symbol>>;
+
+pub struct Bindings {
+    pub variables: Vec<String>,
+    pub rows: Vec<Row>
+}
+
+impl Bindings {
+    pub fn column(&self, variable: &str) -> Option<usize> {
+        self.variables.iter().position(|name| name == variable)
+    }
+}
+
+pub struct PlanStep {
+    pub pattern: usize,
+    pub mask: usize,
+    pub triple_index: graph::TripleIndex,
+    pub estimate: usize
+}
+
+pub struct Plan {
+    pub steps: Vec<PlanStep>
+}
+
+struct Group {
+    patterns: Vec<[Slot; 3]>,
+    optionals: Vec<(usize, Group)>,
+    negations: Vec<Group>,
+    filters: Vec<Condition>
+}
+
+pub struct Query {
+    variables: Vec<String>,
+    group: Group,
+    injective: bool
+}
+
+impl Query {
+    pub fn new(patterns: &[Pattern]) -> Self {
+        let elements: Vec<Element> = patterns.iter().map(|pattern| Element::Pattern(pattern.clone())).collect();
+        Self::from_elements(&elements)
+    }
+
+    pub fn from_elements(elements: &[Element]) -> Self {
+        let mut query = Self{variables: vec![], group: Group{patterns: vec![], optionals: vec![], negations: vec![], filters: vec![]}, injective: false};
+        query.group = query.compile_group(elements);
+        query
+    }
+
+    fn compile_group(&mut self, elements: &[Element]) -> Group {
+        let mut group = Group{patterns: vec![], optionals: vec![], negations: vec![], filters: vec![]};
+        for element in elements {
+            match element {
+                Element::Pattern(pattern) => {
+                    let mut slots = [Slot::Variable(0); 3];
+                    for triple_index in 0..3 {
+                        slots[triple_index] = self.compile_term(&pattern[triple_index]);
+                    }
+                    group.patterns.push(slots);
+                },
+                Element::Optional(elements) => {
+                    let optional = self.compile_group(elements);
+                    group.optionals.push((group.patterns.len(), optional));
+                },
+                Element::NotExists(elements) => {
+                    let negation = self.compile_group(elements);
+                    group.negations.push(negation);
+                },
+                Element::Filter(expression) => {
+                    let filter = self.compile_expression(expression);
+                    group.filters.push(filter);
+                }
+            }
+        }
+        group
+    }
+
+    fn compile_term(&mut self, term: &Term) -> Slot {
+        match term {
+            Term::Constant(symbol) => Slot::Constant(*symbol),
+            Term::Variable(name) => Slot::Variable(self.variable_index(name))
+        }
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Condition {
+        match expression {
+            Expression::Equal(left, right) => Condition::Equal(self.compile_term(left), self.compile_term(right)),
+            Expression::NotEqual(left, right) => Condition::NotEqual(self.compile_term(left), self.compile_term(right)),
+            Expression::Not(operand) => Condition::Not(Box::new(self.compile_expression(operand))),
+            Expression::And(left, right) => Condition::And(Box::new(self.compile_expression(left)), Box::new(self.compile_expression(right))),
+            Expression::Or(left, right) => Condition::Or(Box::new(self.compile_expression(left)), Box::new(self.compile_expression(right))),
+            Expression::Exists(elements) => Condition::Exists(self.compile_group(elements))
+        }
+    }
+
+    pub fn set_injective(&mut self, injective: bool) {
+        self.injective = injective;
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn unify(&self, pattern_index: usize, triple: graph::Triple) -> Option<Row> {
+        let mut row: Row = vec![None; self.variables.len()];
+        for (slot, matched) in self.group.patterns[pattern_index].iter().zip(triple) {
+            match *slot {
+                Slot::Constant(symbol) => {
+                    if symbol != matched {
+                        return None;
+                    }
+                },
+                Slot::Variable(variable) => {
+                    if row[variable].is_some_and(|symbol| symbol != matched) {
+                        return None;
+                    }
+                    row[variable] = Some(matched);
+                }
+            }
+        }
+        Some(row)
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.group.patterns.len()
+    }
+
+    fn variable_index(&mut self, name: &str) -> usize {
+        match self.variables.iter().position(|variable| variable == name) {
+            Some(index) => index,
+            None => {
+                self.variables.push(name.to_string());
+                self.variables.len()-1
+            }
+        }
+    }
+
+    fn explain_group(&self, group: &Group, bound: &mut [bool], indentation: &str, result: &mut String) {
+        let nested_indentation = format!("{}  ", indentation);
+        let mut step_count: usize = 0;
+        let mut start: usize = 0;
+        for (end, optional) in segments(group) {
+            for step in plan_patterns(&group.patterns, start..end, bound).steps {
+                let terms: Vec<String> = group.patterns[step.pattern].iter().map(|slot| match slot {
+                    Slot::Constant(symbol) => format!("{}:{}", symbol.0, symbol.1),
+                    Slot::Variable(variable) => format!("?{}", self.variables[*variable])
+                }).collect();
+                let mask: String = (0..3).map(|triple_index| ['M', 'V', 'I'][(step.mask/[1, 3, 9][triple_index])%3]).collect();
+                step_count += 1;
+                *result += &format!("{}{}. ({}) {} via {:?}, estimated {} per row\n", indentation, step_count, terms.join(", "), mask, step.triple_index, step.estimate);
+            }
+            if let Some(optional) = optional {
+                *result += &format!("{}OPTIONAL\n", indentation);
+                self.explain_group(optional, &mut bound.to_vec(), &nested_indentation, result);
+            }
+            start = end;
+        }
+        for negation in &group.negations {
+            *result += &format!("{}NOT EXISTS\n", indentation);
+            self.explain_group(negation, &mut bound.to_vec(), &nested_indentation, result);
+        }
+        if !group.filters.is_empty() {
+            *result += &format!("{}FILTER {} condition(s)\n", indentation, group.filters.len());
+        }
+    }
+
+    pub fn explain(&self) -> String {
+        let mut result = String::new();
+        self.explain_group(&self.group, &mut vec![false; self.variables.len()], "", &mut result);
+        result
+    }
+
+    pub fn evaluate(&self) -> Bindings {
+        self.evaluate_with_overlay(&HashSet::new())
+    }
+
+    pub fn evaluate_with_overlay(&self, overlay: &HashSet<graph::Triple>) -> Bindings {
+        self.evaluate_bound(&vec![None; self.variables.len()], overlay)
+    }
+
+    pub fn evaluate_bound(&self, bound: &[Option<symbol::Symbol>], overlay: &HashSet<graph::Triple>) -> Bindings {
+        Bindings{
+            variables: self.variables.clone(),
+            rows: evaluate_group(&self.group, bound.to_vec(), overlay, self.injective)
+        }
+    }
+}
+
+fn estimate_pattern(pattern: &[Slot; 3], bound: &[bool]) -> (usize, usize) {
+    let mut masks = [graph::QueryMask::Varying; 3];
+    let mut triple = [symbol::Symbol(0, 0); 3];
+    let mut execution_masks = masks;
+    let mut projection_masks = [graph::QueryMask::Ignore; 3];
+    let mut constant_count: usize = 0;
+    let mut bound_count: usize = 0;
+    let mut unbound_count: usize = 0;
+    for triple_index in 0..3 {
+        match pattern[triple_index] {
+            Slot::Constant(symbol) => {
+                masks[triple_index] = graph::QueryMask::Match;
+                execution_masks[triple_index] = graph::QueryMask::Match;
+                projection_masks[triple_index] = graph::QueryMask::Match;
+                triple[triple_index] = symbol;
+                constant_count += 1;
+            },
+            Slot::Variable(variable) => {
+                if bound[variable] {
+                    execution_masks[triple_index] = graph::QueryMask::Match;
+                    projection_masks[triple_index] = graph::QueryMask::Varying;
+                    bound_count += 1;
+                } else {
+                    unbound_count += 1;
+                }
+            }
+        }
+    }
+    let base = graph::estimate_triples(graph::query_mask(masks), triple);
+    let estimate = if bound_count == 0 {
+        base
+    } else if unbound_count == 0 {
+        base.min(1)
+    } else if constant_count > 0 {
+        let distinct = graph::count_triples(graph::query_mask(projection_masks), triple);
+        base.checked_div(distinct).map_or(0, |quotient| quotient+(base%distinct > 0) as usize)
+    } else {
+        graph::average_matches(graph::query_mask(execution_masks))
+    };
+    (graph::query_mask(execution_masks), estimate)
+}
+
+fn plan_patterns(patterns: &[[Slot; 3]], segment: std::ops::Range<usize>, bound: &mut [bool]) -> Plan {
+    let mut remaining: Vec<usize> = segment.collect();
+    let mut steps: Vec<PlanStep> = vec![];
+    while !remaining.is_empty() {
+        let mut best: Option<(usize, usize, usize)> = None;
+        for (position, pattern_index) in remaining.iter().enumerate() {
+            let (mask, estimate) = estimate_pattern(&patterns[*pattern_index], bound);
+            if best.is_none_or(|(_position, _mask, best_estimate)| estimate < best_estimate) {
+                best = Some((position, mask, estimate));
+            }
+        }
+        let (position, mask, estimate) = best.unwrap();
+        let pattern_index = remaining.remove(position);
+        for slot in &patterns[pattern_index] {
+            if let Slot::Variable(variable) = slot {
+                bound[*variable] = true;
+            }
+        }
+        steps.push(PlanStep{pattern: pattern_index, mask, triple_index: graph::triple_index(mask), estimate});
+    }
+    Plan{steps}
+}
+
+fn segments(group: &Group) -> impl Iterator<Item=(usize, Option<&Group>)> {
+    group.optionals.iter().map(|(position, optional)| (*position, Some(optional))).chain(std::iter::once((group.patterns.len(), None)))
+}
+
+fn evaluate_group(group: &Group, seed: Row, overlay: &HashSet<graph::Triple>, injective: bool) -> Vec<Row> {
+    let mut bound: Vec<bool> = seed.iter().map(|binding| binding.is_some()).collect();
+    let mut rows: Vec<Row> = vec![seed];
+    let mut start: usize = 0;
+    for (end, optional) in segments(group) {
+        for step in plan_patterns(&group.patterns, start..end, &mut bound).steps {
+            let mut next_rows: Vec<Row> = vec![];
+            for row in &rows {
+                match_pattern(&group.patterns[step.pattern], row, overlay, injective, &mut next_rows);
+            }
+            rows = next_rows;
+            if rows.is_empty() {
+                return rows;
+            }
+        }
+        if let Some(optional) = optional {
+            let mut next_rows: Vec<Row> = vec![];
+            for row in rows {
+                let extended_rows = evaluate_group(optional, row.clone(), overlay, injective);
+                if extended_rows.is_empty() {
+                    next_rows.push(row);
+                } else {
+                    next_rows.extend(extended_rows);
+                }
+            }
+            rows = next_rows;
+        }
+        start = end;
+    }
+    for negation in &group.negations {
+        rows.retain(|row| evaluate_group(negation, row.clone(), overlay, injective).is_empty());
+    }
+    for filter in &group.filters {
+        rows.retain(|row| evaluate_condition(filter, row, overlay, injective) == Some(true));
+    }
+    rows
+}
+
+fn evaluate_condition(condition: &Condition, row: &[Option<symbol::Symbol>], overlay: &HashSet<graph::Triple>, injective: bool) -> Option<bool> {
+    let resolve = |slot: &Slot| match slot {
+        Slot::Constant(symbol) => Some(*symbol),
+        Slot::Variable(variable) => row[*variable]
+    };
+    match condition {
+        Condition::Equal(left, right) => Some(resolve(left)? == resolve(right)?),
+        Condition::NotEqual(left, right) => Some(resolve(left)? != resolve(right)?),
+        Condition::Not(operand) => evaluate_condition(operand, row, overlay, injective).map(|value| !value),
+        Condition::And(left, right) => match (evaluate_condition(left, row, overlay, injective), evaluate_condition(right, row, overlay, injective)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None
+        },
+        Condition::Or(left, right) => match (evaluate_condition(left, row, overlay, injective), evaluate_condition(right, row, overlay, injective)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None
+        },
+        Condition::Exists(group) => Some(!evaluate_group(group, row.to_vec(), overlay, injective).is_empty())
+    }
+}
+
+fn match_pattern(pattern: &[Slot; 3], row: &[Option<symbol::Symbol>], overlay: &HashSet<graph::Triple>, injective: bool, result: &mut Vec<Row>) {
+    let mut masks = [graph::QueryMask::Varying; 3];
+    let mut triple = [symbol::Symbol(0, 0); 3];
+    for triple_index in 0..3 {
+        let bound = match pattern[triple_index] {
+            Slot::Constant(symbol) => Some(symbol),
+            Slot::Variable(variable) => row[variable]
+        };
+        if let Some(symbol) = bound {
+            masks[triple_index] = graph::QueryMask::Match;
+            triple[triple_index] = symbol;
+        }
+    }
+    let mut matched_triples = graph::query_triples(graph::query_mask(masks), triple);
+    matched_triples.extend(overlay.iter().filter(|overlay_triple| {
+        (0..3).all(|triple_index| masks[triple_index] != graph::QueryMask::Match || overlay_triple[triple_index] == triple[triple_index])
+    }));
+    'triples: for matched_triple in matched_triples {
+        let mut extended_row = row.to_vec();
+        for triple_index in 0..3 {
+            if let Slot::Variable(variable) = pattern[triple_index] {
+                match extended_row[variable] {
+                    Some(symbol) => {
+                        if symbol != matched_triple[triple_index] {
+                            continue 'triples;
+                        }
+                    },
+                    None => {
+                        if injective && extended_row.contains(&Some(matched_triple[triple_index])) {
+                            continue 'triples;
+                        }
+                        extended_row[variable] = Some(matched_triple[triple_index]);
+                    }
+                }
+            }
+        }
+        result.push(extended_row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str) -> Term {
+        Term::Variable(name.to_string())
+    }
+
+    fn pattern(entity: &str, attribute: symbol::Symbol, value: &str) -> Element {
+        Element::Pattern([variable(entity), Term::Constant(attribute), variable(value)])
+    }
+
+    fn setup() -> ([symbol::Symbol; 8], symbol::Symbol, symbol::Symbol) {
+        graph::manifest_symbol(symbol::Symbol(0, 0));
+        graph::manifest_symbol(symbol::Symbol(0, 1));
+        let symbols: [symbol::Symbol; 8] = std::array::from_fn(|_index| graph::create_symbol(1));
+        let [p, q] = [graph::create_symbol(1), graph::create_symbol(1)];
+        let [a, b, c, d, e, f, g, h] = symbols;
+        for triple in [[a, p, b], [b, p, c], [c, q, d], [e, p, f], [g, q, h]] {
+            assert!(graph::set_triple(triple, true));
+        }
+        (symbols, p, q)
+    }
+
+    fn evaluate(elements: &[Element]) -> Vec<Row> {
+        let bindings = Query::from_elements(elements).evaluate();
+        assert_eq!(bindings.variables, ["x", "y", "z", "w"]);
+        let mut rows = bindings.rows;
+        rows.sort_unstable();
+        rows
+    }
+
+    #[test]
+    fn optional_joins_where_it_appears() {
+        let ([a, b, c, d, e, f, g, h], p, q) = setup();
+        let mut expected = vec![
+            vec![Some(a), Some(b), Some(c), Some(d)],
+            vec![Some(b), Some(c), Some(c), Some(d)],
+            vec![Some(b), Some(c), Some(g), Some(h)],
+            vec![Some(e), Some(f), Some(c), Some(d)],
+            vec![Some(e), Some(f), Some(g), Some(h)]
+        ];
+        expected.sort_unstable();
+        assert_eq!(evaluate(&[pattern("x", p, "y"), Element::Optional(vec![pattern("y", p, "z")]), pattern("z", q, "w")]), expected);
+    }
+
+    #[test]
+    fn optional_after_required_patterns_keeps_incompatible_rows() {
+        let ([a, b, c, d, e, f, g, h], p, q) = setup();
+        let mut expected: Vec<Row> = vec![];
+        for (x, y) in [(a, b), (b, c), (e, f)] {
+            for (z, w) in [(c, d), (g, h)] {
+                expected.push(vec![Some(x), Some(y), Some(z), Some(w)]);
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(evaluate(&[pattern("x", p, "y"), pattern("z", q, "w"), Element::Optional(vec![pattern("y", p, "z")])]), expected);
+    }
+}
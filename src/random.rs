@@ -0,0 +1,35 @@
+pub struct SplitMix64 {
+    state: u64
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self{state: seed}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut mixed = self.state;
+        mixed = (mixed^(mixed>>30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        mixed = (mixed^(mixed>>27)).wrapping_mul(0x94d049bb133111eb);
+        mixed^(mixed>>31)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        let bound = bound as u64;
+        let zone = u64::MAX-u64::MAX%bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value%bound) as usize;
+            }
+        }
+    }
+
+    pub fn shuffle_prefix<T>(&mut self, items: &mut [T], count: usize) {
+        for index in 0..count.min(items.len()) {
+            let other = index+self.below(items.len()-index);
+            items.swap(index, other);
+        }
+    }
+}
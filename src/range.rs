@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use crate::symbol;
+use crate::graph;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Unsigned,
+    Signed,
+    Float
+}
+
+const SIGN_BIT: u64 = 1<<63;
+
+pub fn unsigned_key(value: u64) -> u64 {
+    value
+}
+
+pub fn signed_key(value: i64) -> u64 {
+    (value as u64)^SIGN_BIT
+}
+
+pub fn float_key(value: f64) -> u64 {
+    let bits = if value == 0.0 { 0 } else { value.to_bits() };
+    if bits&SIGN_BIT == 0 { bits|SIGN_BIT } else { !bits }
+}
+
+pub fn encode_key(encoding: Encoding, bits: u64, data_length: usize) -> Option<u64> {
+    if data_length == 0 || data_length > 64 {
+        return None;
+    }
+    match encoding {
+        Encoding::Unsigned => Some(unsigned_key(bits)),
+        Encoding::Signed => {
+            let shift = 64-data_length as u32;
+            Some(signed_key(((bits<<shift) as i64)>>shift))
+        },
+        Encoding::Float => {
+            let value = match data_length {
+                32 => f32::from_bits(bits as u32) as f64,
+                64 => f64::from_bits(bits),
+                _ => { return None; }
+            };
+            if value.is_nan() { None } else { Some(float_key(value)) }
+        }
+    }
+}
+
+struct RangeIndex {
+    encoding: Encoding,
+    entries: BTreeSet<(u64, symbol::Identity)>,
+    keys: HashMap<symbol::Identity, u64>
+}
+
+impl RangeIndex {
+    fn remove(&mut self, symbol_identity: symbol::Identity) {
+        if let Some(key) = self.keys.remove(&symbol_identity) {
+            self.entries.remove(&(key, symbol_identity));
+        }
+    }
+
+    fn insert(&mut self, symbol_identity: symbol::Identity, bits: u64, data_length: usize) {
+        if let Some(key) = encode_key(self.encoding, bits, data_length) {
+            self.keys.insert(symbol_identity, key);
+            self.entries.insert((key, symbol_identity));
+        }
+    }
+}
+
+thread_local!(static RANGE_INDEX: RefCell<HashMap<symbol::Identity, RangeIndex>> = RefCell::new(HashMap::new()));
+
+
+
+pub fn is_indexed(namespace_identity: symbol::Identity) -> bool {
+    RANGE_INDEX.with(|range_index_cell| range_index_cell.borrow().contains_key(&namespace_identity))
+}
+
+pub fn create_range_index(namespace_identity: symbol::Identity, encoding: Encoding) -> bool {
+    if is_indexed(namespace_identity) {
+        return false;
+    }
+    let mut range_index = RangeIndex{encoding, entries: BTreeSet::new(), keys: HashMap::new()};
+    for (symbol, bits, data_length) in graph::leading_bits_in_namespace(namespace_identity) {
+        range_index.insert(symbol.1, bits, data_length);
+    }
+    RANGE_INDEX.with(|range_index_cell| {
+        range_index_cell.borrow_mut().insert(namespace_identity, range_index);
+    });
+    true
+}
+
+pub fn drop_range_index(namespace_identity: symbol::Identity) -> bool {
+    RANGE_INDEX.with(|range_index_cell| range_index_cell.borrow_mut().remove(&namespace_identity).is_some())
+}
+
+pub fn data_changed(symbol: symbol::Symbol, value: Option<(u64, usize)>) {
+    RANGE_INDEX.with(|range_index_cell| {
+        if let Some(range_index) = range_index_cell.borrow_mut().get_mut(&symbol.0) {
+            range_index.remove(symbol.1);
+            if let Some((bits, data_length)) = value {
+                range_index.insert(symbol.1, bits, data_length);
+            }
+        }
+    })
+}
+
+pub fn query_range(namespace_identity: symbol::Identity, lower_key: u64, upper_key: u64) -> Option<Vec<symbol::Symbol>> {
+    RANGE_INDEX.with(|range_index_cell| {
+        let range_index = range_index_cell.borrow();
+        let range_index = range_index.get(&namespace_identity)?;
+        if lower_key > upper_key {
+            return Some(vec![]);
+        }
+        Some(range_index.entries.range((lower_key, 0)..=(upper_key, symbol::Identity::MAX))
+            .map(|(_key, symbol_identity)| symbol::Symbol(namespace_identity, *symbol_identity)).collect())
+    })
+}
+
+fn query_range_encoded(namespace_identity: symbol::Identity, encoding: Encoding, lower_key: u64, upper_key: u64) -> Option<Vec<symbol::Symbol>> {
+    let indexed_encoding = RANGE_INDEX.with(|range_index_cell| range_index_cell.borrow().get(&namespace_identity).map(|range_index| range_index.encoding))?;
+    if indexed_encoding != encoding {
+        return None;
+    }
+    query_range(namespace_identity, lower_key, upper_key)
+}
+
+pub fn query_range_unsigned(namespace_identity: symbol::Identity, min: u64, max: u64) -> Option<Vec<symbol::Symbol>> {
+    query_range_encoded(namespace_identity, Encoding::Unsigned, unsigned_key(min), unsigned_key(max))
+}
+
+pub fn query_range_signed(namespace_identity: symbol::Identity, min: i64, max: i64) -> Option<Vec<symbol::Symbol>> {
+    query_range_encoded(namespace_identity, Encoding::Signed, signed_key(min), signed_key(max))
+}
+
+pub fn query_range_float(namespace_identity: symbol::Identity, min: f64, max: f64) -> Option<Vec<symbol::Symbol>> {
+    if min.is_nan() || max.is_nan() {
+        return None;
+    }
+    query_range_encoded(namespace_identity, Encoding::Float, float_key(min), float_key(max))
+}
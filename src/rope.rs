@@ -0,0 +1,380 @@
+use crate::bitops;
+use crate::random;
+
+const CHUNK_LENGTH: usize = 64*bitops::ARCHITECTURE_SIZE;
+const READ_BLOCK_LENGTH: usize = 16*CHUNK_LENGTH;
+
+fn word_count(length: usize) -> usize {
+    length.div_ceil(bitops::ARCHITECTURE_SIZE)
+}
+
+fn extract_bits(src: &[usize], offset: usize, length: usize) -> Vec<usize> {
+    bitops::BitwiseRead::new(src, length, offset).collect()
+}
+
+fn append_bits(dst: &mut Vec<usize>, dst_length: &mut usize, src: &[usize], src_offset: usize, length: usize) {
+    dst.resize(word_count(*dst_length+length), 0);
+    bitops::bitwise_copy_nonoverlapping(dst, src, *dst_length, src_offset, length);
+    *dst_length += length;
+}
+
+struct Node {
+    chunk_content: Vec<usize>,
+    chunk_length: usize,
+    total_length: usize,
+    priority: u64,
+    left: Option<Box<Node>>,
+    right: Option<Box<Node>>
+}
+
+type Tree = Option<Box<Node>>;
+
+impl Node {
+    fn new(chunk_content: Vec<usize>, chunk_length: usize, priority: u64) -> Box<Self> {
+        Box::new(Self{chunk_content, chunk_length, total_length: chunk_length, priority, left: None, right: None})
+    }
+
+    fn update(&mut self) {
+        self.total_length = total_length(&self.left)+self.chunk_length+total_length(&self.right);
+    }
+}
+
+fn total_length(tree: &Tree) -> usize {
+    tree.as_ref().map_or(0, |node| node.total_length)
+}
+
+fn merge(left: Tree, right: Tree) -> Tree {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                left.update();
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                right.update();
+                Some(right)
+            }
+        }
+    }
+}
+
+fn split(tree: Tree, offset: usize, random: &mut random::SplitMix64) -> (Tree, Tree) {
+    let mut node = match tree {
+        Some(node) => node,
+        None => { return (None, None); }
+    };
+    let left_length = total_length(&node.left);
+    if offset <= left_length {
+        let (left, right) = split(node.left.take(), offset, random);
+        node.left = right;
+        node.update();
+        (left, Some(node))
+    } else if offset >= left_length+node.chunk_length {
+        let (left, right) = split(node.right.take(), offset-left_length-node.chunk_length, random);
+        node.right = left;
+        node.update();
+        (Some(node), right)
+    } else {
+        let chunk_offset = offset-left_length;
+        let tail_length = node.chunk_length-chunk_offset;
+        let tail = Node::new(extract_bits(&node.chunk_content, chunk_offset, tail_length), tail_length, random.next_u64());
+        node.chunk_content = extract_bits(&node.chunk_content, 0, chunk_offset);
+        node.chunk_length = chunk_offset;
+        let right = merge(Some(tail), node.right.take());
+        node.update();
+        (Some(node), right)
+    }
+}
+
+fn pop_first(tree: Tree) -> (Tree, Tree) {
+    let mut node = match tree {
+        Some(node) => node,
+        None => { return (None, None); }
+    };
+    if node.left.is_some() {
+        let (first, rest) = pop_first(node.left.take());
+        node.left = rest;
+        node.update();
+        (first, Some(node))
+    } else {
+        let rest = node.right.take();
+        node.update();
+        (Some(node), rest)
+    }
+}
+
+fn pop_last(tree: Tree) -> (Tree, Tree) {
+    let mut node = match tree {
+        Some(node) => node,
+        None => { return (None, None); }
+    };
+    if node.right.is_some() {
+        let (rest, last) = pop_last(node.right.take());
+        node.right = rest;
+        node.update();
+        (Some(node), last)
+    } else {
+        let rest = node.left.take();
+        node.update();
+        (rest, Some(node))
+    }
+}
+
+fn read_tree(tree: &Tree, offset: usize, length: usize, dst: &mut [usize], dst_offset: usize) {
+    let node = match tree {
+        Some(node) if length > 0 => node,
+        _ => { return; }
+    };
+    let left_length = total_length(&node.left);
+    let chunk_end = left_length+node.chunk_length;
+    let end = offset+length;
+    if offset < left_length {
+        read_tree(&node.left, offset, end.min(left_length)-offset, dst, dst_offset);
+    }
+    if offset < chunk_end && end > left_length {
+        let begin = offset.max(left_length);
+        bitops::bitwise_copy_nonoverlapping(dst, &node.chunk_content, dst_offset+begin-offset, begin-left_length, end.min(chunk_end)-begin);
+    }
+    if end > chunk_end {
+        let begin = offset.max(chunk_end);
+        read_tree(&node.right, begin-chunk_end, end-begin, dst, dst_offset+begin-offset);
+    }
+}
+
+fn write_tree(tree: &mut Tree, offset: usize, length: usize, src: &[usize], src_offset: usize) {
+    let node = match tree {
+        Some(node) if length > 0 => node,
+        _ => { return; }
+    };
+    let left_length = total_length(&node.left);
+    let chunk_end = left_length+node.chunk_length;
+    let end = offset+length;
+    if offset < left_length {
+        write_tree(&mut node.left, offset, end.min(left_length)-offset, src, src_offset);
+    }
+    if offset < chunk_end && end > left_length {
+        let begin = offset.max(left_length);
+        bitops::bitwise_copy_nonoverlapping(&mut node.chunk_content, src, begin-left_length, src_offset+begin-offset, end.min(chunk_end)-begin);
+    }
+    if end > chunk_end {
+        let begin = offset.max(chunk_end);
+        write_tree(&mut node.right, begin-chunk_end, end-begin, src, src_offset+begin-offset);
+    }
+}
+
+pub struct BitRope {
+    root: Tree,
+    tail_content: Vec<usize>,
+    tail_length: usize,
+    random: random::SplitMix64
+}
+
+impl BitRope {
+    pub fn new() -> Self {
+        Self{root: None, tail_content: vec![], tail_length: 0, random: random::SplitMix64::new(0)}
+    }
+
+    pub fn len(&self) -> usize {
+        total_length(&self.root)+self.tail_length
+    }
+
+    // The tree is allocated exactly, so only the tail adds capacity beyond the length.
+    pub fn capacity(&self) -> usize {
+        total_length(&self.root)+self.tail_content.capacity()*bitops::ARCHITECTURE_SIZE
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let word_total = word_count(self.tail_length+additional);
+        if word_total > self.tail_content.len() {
+            self.tail_content.reserve(word_total-self.tail_content.len());
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.tail_content.shrink_to_fit();
+    }
+
+    fn build(&mut self, content: &[usize], length: usize) -> Tree {
+        let mut tree: Tree = None;
+        let mut offset: usize = 0;
+        while offset < length {
+            let chunk_length = CHUNK_LENGTH.min(length-offset);
+            tree = merge(tree, Some(Node::new(extract_bits(content, offset, chunk_length), chunk_length, self.random.next_u64())));
+            offset += chunk_length;
+        }
+        tree
+    }
+
+    fn join(&mut self, left: Tree, middle_content: &[usize], middle_length: usize, right: Tree) {
+        let (left, left_last) = pop_last(left);
+        let (right_first, right) = pop_first(right);
+        let mut content: Vec<usize> = vec![];
+        let mut length: usize = 0;
+        if let Some(node) = &left_last {
+            append_bits(&mut content, &mut length, &node.chunk_content, 0, node.chunk_length);
+        }
+        append_bits(&mut content, &mut length, middle_content, 0, middle_length);
+        if let Some(node) = &right_first {
+            append_bits(&mut content, &mut length, &node.chunk_content, 0, node.chunk_length);
+        }
+        let middle = self.build(&content, length);
+        self.root = merge(merge(left, middle), right);
+    }
+
+    fn flush_tail(&mut self) {
+        if self.tail_length == 0 {
+            return;
+        }
+        let tail_content = std::mem::take(&mut self.tail_content);
+        let root = self.root.take();
+        self.join(root, &tail_content, self.tail_length, None);
+        if tail_content.capacity()*bitops::ARCHITECTURE_SIZE <= 4*CHUNK_LENGTH {
+            self.tail_content = tail_content;
+            self.tail_content.clear();
+        }
+        self.tail_length = 0;
+    }
+
+    pub fn insert_zeros(&mut self, offset: usize, length: usize) {
+        assert!(offset <= self.len());
+        if length == 0 {
+            return;
+        }
+        if offset == self.len() {
+            self.tail_length += length;
+            self.tail_content.resize(word_count(self.tail_length), 0);
+            return;
+        }
+        if offset > total_length(&self.root) {
+            self.flush_tail();
+        }
+        let (left, right) = split(self.root.take(), offset, &mut self.random);
+        self.join(left, &vec![0; word_count(length)], length, right);
+    }
+
+    pub fn remove(&mut self, offset: usize, length: usize) {
+        assert!(offset+length <= self.len());
+        if length == 0 {
+            return;
+        }
+        let tree_length = total_length(&self.root);
+        if offset >= tree_length && offset+length == self.len() {
+            self.tail_length -= length;
+            self.tail_content.truncate(word_count(self.tail_length));
+            if let Some(last_word) = self.tail_content.last_mut() {
+                *last_word &= bitops::lsb_bitmask((self.tail_length-1)%bitops::ARCHITECTURE_SIZE+1);
+            }
+            return;
+        }
+        if offset+length > tree_length {
+            self.flush_tail();
+        }
+        let (left, rest) = split(self.root.take(), offset, &mut self.random);
+        let (_removed, right) = split(rest, length, &mut self.random);
+        self.join(left, &[], 0, right);
+    }
+
+    pub fn read(&self, offset: usize, length: usize) -> Vec<usize> {
+        assert!(offset+length <= self.len());
+        let mut dst: Vec<usize> = vec![0; word_count(length)];
+        let tree_length = total_length(&self.root);
+        let end = offset+length;
+        if offset < tree_length {
+            read_tree(&self.root, offset, end.min(tree_length)-offset, &mut dst, 0);
+        }
+        if end > tree_length {
+            let begin = offset.max(tree_length);
+            bitops::bitwise_copy_nonoverlapping(&mut dst, &self.tail_content, begin-offset, begin-tree_length, end-begin);
+        }
+        dst
+    }
+
+    pub fn words(&self) -> impl Iterator<Item=usize>+'_ {
+        let length = self.len();
+        (0..length).step_by(READ_BLOCK_LENGTH).flat_map(move |offset| self.read(offset, READ_BLOCK_LENGTH.min(length-offset)))
+    }
+
+    pub fn write(&mut self, offset: usize, length: usize, src: &[usize]) {
+        assert!(offset+length <= self.len());
+        let tree_length = total_length(&self.root);
+        let end = offset+length;
+        if offset < tree_length {
+            write_tree(&mut self.root, offset, end.min(tree_length)-offset, src, 0);
+        }
+        if end > tree_length {
+            let begin = offset.max(tree_length);
+            bitops::bitwise_copy_nonoverlapping(&mut self.tail_content, src, begin-tree_length, begin-offset, end-begin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(bits: &[bool]) -> Vec<usize> {
+        let mut words = vec![0; word_count(bits.len())];
+        for (index, bit) in bits.iter().enumerate() {
+            words[index/bitops::ARCHITECTURE_SIZE] |= (*bit as usize)<<(index%bitops::ARCHITECTURE_SIZE);
+        }
+        words
+    }
+
+    #[test]
+    fn matches_bit_vector_model() {
+        for seed in 0..100 {
+            let mut random = random::SplitMix64::new(seed);
+            let mut rope = BitRope::new();
+            let mut model: Vec<bool> = vec![];
+            let max_length = [8, 300, 2*CHUNK_LENGTH][seed as usize%3];
+            for _operation in 0..400 {
+                match random.below(7) {
+                    0 | 1 => {
+                        let offset = if random.below(2) == 0 { model.len() } else { random.below(model.len()+1) };
+                        let length = random.below(max_length+1);
+                        rope.insert_zeros(offset, length);
+                        model.splice(offset..offset, vec![false; length]);
+                    },
+                    2 | 3 => {
+                        let offset = random.below(model.len()+1);
+                        let length = random.below((model.len()-offset).min(max_length)+1);
+                        rope.remove(offset, length);
+                        model.drain(offset..offset+length);
+                    },
+                    4 => {
+                        let offset = random.below(model.len()+1);
+                        let length = random.below((model.len()-offset).min(2*CHUNK_LENGTH)+1);
+                        let bits: Vec<bool> = (0..length).map(|_index| random.below(2) == 1).collect();
+                        rope.write(offset, length, &pack(&bits));
+                        model[offset..offset+length].copy_from_slice(&bits);
+                    },
+                    5 => {
+                        rope.reserve(random.below(max_length+1));
+                    },
+                    _ => {
+                        rope.shrink_to_fit();
+                    }
+                }
+                assert_eq!(rope.len(), model.len());
+                assert!(rope.capacity() >= rope.len());
+                let offset = random.below(model.len()+1);
+                let length = random.below((model.len()-offset).min(2*CHUNK_LENGTH)+1);
+                assert_eq!(rope.read(offset, length), pack(&model[offset..offset+length]));
+            }
+            assert_eq!(rope.words().collect::<Vec<usize>>(), pack(&model));
+        }
+    }
+
+    #[test]
+    fn flush_releases_large_tail() {
+        let mut rope = BitRope::new();
+        rope.reserve(100*CHUNK_LENGTH);
+        rope.insert_zeros(0, 10*CHUNK_LENGTH);
+        assert!(rope.capacity() >= 100*CHUNK_LENGTH);
+        rope.insert_zeros(CHUNK_LENGTH, 1);
+        assert_eq!(rope.capacity(), rope.len());
+    }
+}
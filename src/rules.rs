@@ -0,0 +1,145 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use crate::graph;
+use crate::query;
+
+pub struct Rule {
+    pub head: query::Pattern,
+    pub body: Vec<query::Pattern>
+}
+
+impl Rule {
+    fn is_range_restricted(&self) -> bool {
+        self.head.iter().all(|head_term| match head_term {
+            query::Term::Constant(_symbol) => true,
+            query::Term::Variable(_name) => self.body.iter().any(|pattern| pattern.contains(head_term))
+        })
+    }
+
+    fn derive(&self, overlay: &HashSet<graph::Triple>) -> Vec<graph::Triple> {
+        let bindings = query::Query::new(&self.body).evaluate_with_overlay(overlay);
+        bindings.rows.iter().map(|row| {
+            std::array::from_fn(|triple_index| match &self.head[triple_index] {
+                query::Term::Constant(symbol) => *symbol,
+                query::Term::Variable(name) => row[bindings.column(name).unwrap()].unwrap()
+            })
+        }).collect()
+    }
+}
+
+thread_local!(static RULES: RefCell<Vec<Rule>> = const { RefCell::new(Vec::new()) });
+thread_local!(static DERIVED: RefCell<HashSet<graph::Triple>> = RefCell::new(HashSet::new()));
+thread_local!(static OVERLAY: RefCell<Option<(usize, HashSet<graph::Triple>)>> = const { RefCell::new(None) });
+
+
+
+pub fn add_rule(rule: Rule) -> bool {
+    if rule.body.is_empty() || !rule.is_range_restricted() {
+        return false;
+    }
+    RULES.with(|rules_cell| rules_cell.borrow_mut().push(rule));
+    OVERLAY.with(|overlay_cell| overlay_cell.borrow_mut().take());
+    true
+}
+
+pub fn clear_rules() {
+    RULES.with(|rules_cell| rules_cell.borrow_mut().clear());
+    OVERLAY.with(|overlay_cell| overlay_cell.borrow_mut().take());
+}
+
+pub fn triple_changed(triple: graph::Triple) {
+    DERIVED.with(|derived_cell| derived_cell.borrow_mut().remove(&triple));
+}
+
+pub fn is_derived(triple: graph::Triple) -> bool {
+    DERIVED.with(|derived_cell| derived_cell.borrow().contains(&triple)) && graph::count_triples(graph::query_mask([graph::QueryMask::Match; 3]), triple) == 1
+}
+
+pub fn materialize() -> usize {
+    RULES.with(|rules_cell| {
+        let rules = rules_cell.borrow();
+        let mut result: usize = 0;
+        loop {
+            let mut linked: usize = 0;
+            for rule in rules.iter() {
+                for triple in rule.derive(&HashSet::new()) {
+                    if graph::set_triple(triple, true) {
+                        DERIVED.with(|derived_cell| derived_cell.borrow_mut().insert(triple));
+                        linked += 1;
+                    }
+                }
+            }
+            if linked == 0 {
+                return result;
+            }
+            result += linked;
+        }
+    })
+}
+
+pub fn retract_derived() -> usize {
+    let derived = DERIVED.with(|derived_cell| std::mem::take(&mut *derived_cell.borrow_mut()));
+    derived.into_iter().filter(|triple| graph::set_triple(*triple, false)).count()
+}
+
+fn derive_overlay() -> HashSet<graph::Triple> {
+    RULES.with(|rules_cell| {
+        let rules = rules_cell.borrow();
+        let mut overlay: HashSet<graph::Triple> = HashSet::new();
+        loop {
+            let mut derived: Vec<graph::Triple> = vec![];
+            for rule in rules.iter() {
+                for triple in rule.derive(&overlay) {
+                    if !overlay.contains(&triple) && graph::count_triples(graph::query_mask([graph::QueryMask::Match; 3]), triple) == 0 {
+                        derived.push(triple);
+                    }
+                }
+            }
+            if derived.is_empty() {
+                return overlay;
+            }
+            overlay.extend(derived);
+        }
+    })
+}
+
+pub fn query(patterns: &[query::Pattern]) -> query::Bindings {
+    OVERLAY.with(|overlay_cell| {
+        let mut cached = overlay_cell.borrow_mut();
+        let generation = graph::generation();
+        if cached.as_ref().is_none_or(|(cached_generation, _overlay)| *cached_generation != generation) {
+            *cached = Some((generation, derive_overlay()));
+        }
+        query::Query::new(patterns).evaluate_with_overlay(&cached.as_ref().unwrap().1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbol;
+
+    fn variable(name: &str) -> query::Term {
+        query::Term::Variable(name.to_string())
+    }
+
+    #[test]
+    fn cached_overlay_follows_writes_and_rules() {
+        graph::manifest_symbol(symbol::Symbol(0, 0));
+        graph::manifest_symbol(symbol::Symbol(0, 1));
+        let [a, b, c, p, q] = [graph::create_symbol(1), graph::create_symbol(1), graph::create_symbol(1), graph::create_symbol(1), graph::create_symbol(1)];
+        let edge = |attribute: symbol::Symbol| [variable("x"), query::Term::Constant(attribute), variable("y")];
+        assert!(add_rule(Rule{head: edge(q), body: vec![edge(p)]}));
+        assert!(graph::set_triple([a, p, b], true));
+        assert_eq!(query(&[edge(q)]).rows, vec![vec![Some(a), Some(b)]]);
+        let generation = graph::generation();
+        assert_eq!(query(&[edge(q)]).rows.len(), 1);
+        assert!(OVERLAY.with(|overlay_cell| overlay_cell.borrow().as_ref().is_some_and(|(cached_generation, _overlay)| *cached_generation == generation)));
+        assert!(graph::set_triple([b, p, c], true));
+        assert_eq!(query(&[edge(q)]).rows.len(), 2);
+        assert!(add_rule(Rule{head: [variable("y"), query::Term::Constant(q), variable("x")], body: vec![edge(q)]}));
+        assert_eq!(query(&[edge(q)]).rows.len(), 4);
+        clear_rules();
+        assert!(query(&[edge(q)]).rows.is_empty());
+    }
+}
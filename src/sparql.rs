@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::symbol;
+use crate::graph;
+use crate::query;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Variable(String),
+    Name(String),
+    Integer(usize),
+    Punctuation(&'static str),
+    End
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Variable(name) => format!("variable ?{}", name),
+            Token::Name(name) => format!("'{}'", name),
+            Token::Integer(value) => format!("integer {}", value),
+            Token::Punctuation(punctuation) => format!("'{}'", punctuation),
+            Token::End => "end of input".to_string()
+        }
+    }
+}
+
+struct Lexeme {
+    token: Token,
+    line: usize,
+    column: usize
+}
+
+const PUNCTUATIONS: [&str; 14] = ["!=", "&&", "||", "{", "}", "(", ")", ".", "*", ":", "=", "!", "<", ">"];
+
+fn tokenize(text: &str) -> Result<Vec<Lexeme>, ParseError> {
+    let characters: Vec<char> = text.chars().collect();
+    let mut lexemes: Vec<Lexeme> = vec![];
+    let (mut index, mut line, mut column) = (0, 1, 1);
+    while index < characters.len() {
+        let character = characters[index];
+        if character == '\n' {
+            index += 1;
+            line += 1;
+            column = 1;
+            continue;
+        }
+        if character.is_whitespace() {
+            index += 1;
+            column += 1;
+            continue;
+        }
+        if character == '#' {
+            while index < characters.len() && characters[index] != '\n' {
+                index += 1;
+            }
+            continue;
+        }
+        let start = index;
+        let token = if character == '?' || character == '$' {
+            index += 1;
+            while index < characters.len() && (characters[index].is_alphanumeric() || characters[index] == '_') {
+                index += 1;
+            }
+            if index == start+1 {
+                return Err(ParseError{line, column, message: format!("expected variable name after '{}'", character)});
+            }
+            Token::Variable(characters[start+1..index].iter().collect())
+        } else if character.is_ascii_digit() {
+            while index < characters.len() && characters[index].is_ascii_digit() {
+                index += 1;
+            }
+            let digits: String = characters[start..index].iter().collect();
+            match digits.parse::<usize>() {
+                Ok(value) => Token::Integer(value),
+                Err(_) => { return Err(ParseError{line, column, message: format!("integer {} is out of range", digits)}); }
+            }
+        } else if character.is_alphabetic() || character == '_' {
+            while index < characters.len() && (characters[index].is_alphanumeric() || characters[index] == '_' || characters[index] == '-') {
+                index += 1;
+            }
+            Token::Name(characters[start..index].iter().collect())
+        } else {
+            let punctuation = PUNCTUATIONS.iter().find(|punctuation| {
+                punctuation.chars().enumerate().all(|(offset, expected)| characters.get(index+offset) == Some(&expected))
+            });
+            match punctuation {
+                Some(punctuation) => {
+                    index += punctuation.len();
+                    Token::Punctuation(punctuation)
+                },
+                None => { return Err(ParseError{line, column, message: format!("unexpected character '{}'", character)}); }
+            }
+        };
+        lexemes.push(Lexeme{token, line, column});
+        column += index-start;
+    }
+    lexemes.push(Lexeme{token: Token::End, line, column});
+    Ok(lexemes)
+}
+
+pub struct SelectQuery {
+    variables: Vec<String>,
+    limit: Option<usize>,
+    query: query::Query
+}
+
+impl SelectQuery {
+    pub fn evaluate(&self) -> query::Bindings {
+        let bindings = self.query.evaluate();
+        let columns: Vec<Option<usize>> = self.variables.iter().map(|variable| bindings.column(variable)).collect();
+        let mut rows: Vec<Vec<Option<symbol::Symbol>>> = bindings.rows.iter().map(|row| {
+            columns.iter().map(|column| column.and_then(|column| row[column])).collect()
+        }).collect();
+        if graph::ordered_results() {
+            rows.sort_unstable();
+        }
+        if let Some(limit) = self.limit {
+            rows.truncate(limit);
+        }
+        query::Bindings{variables: self.variables.clone(), rows}
+    }
+}
+
+struct Parser {
+    lexemes: Vec<Lexeme>,
+    position: usize,
+    prefixes: HashMap<String, symbol::Identity>
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.lexemes[self.position].token
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        &self.lexemes[(self.position+offset).min(self.lexemes.len()-1)].token
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.lexemes[self.position].token.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        let lexeme = &self.lexemes[self.position];
+        Err(ParseError{line: lexeme.line, column: lexeme.column, message})
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        self.error(format!("expected {}, found {}", expected, self.peek().describe()))
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Token::Name(name) => name.eq_ignore_ascii_case(keyword) && *self.peek_at(1) != Token::Punctuation(":"),
+            _ => false
+        }
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        if self.is_keyword(keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.accept_keyword(keyword) {
+            Ok(())
+        } else {
+            self.unexpected(keyword)
+        }
+    }
+
+    fn accept_punctuation(&mut self, punctuation: &str) -> bool {
+        if matches!(self.peek(), Token::Punctuation(found) if *found == punctuation) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punctuation(&mut self, punctuation: &str) -> Result<(), ParseError> {
+        if self.accept_punctuation(punctuation) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{}'", punctuation))
+        }
+    }
+
+    fn expect_integer(&mut self) -> Result<usize, ParseError> {
+        match *self.peek() {
+            Token::Integer(value) => {
+                self.advance();
+                Ok(value)
+            },
+            _ => self.unexpected("integer")
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<SelectQuery, ParseError> {
+        while self.accept_keyword("PREFIX") {
+            let name = match self.peek().clone() {
+                Token::Name(name) => name,
+                _ => { return self.unexpected("prefix name"); }
+            };
+            self.advance();
+            self.expect_punctuation(":")?;
+            let bracketed = self.accept_punctuation("<");
+            let namespace_identity = self.expect_integer()?;
+            if bracketed {
+                self.expect_punctuation(">")?;
+            }
+            self.prefixes.insert(name, namespace_identity);
+        }
+        self.expect_keyword("SELECT")?;
+        let mut selected: Option<Vec<String>> = None;
+        if !self.accept_punctuation("*") {
+            let mut variables: Vec<String> = vec![];
+            while let Token::Variable(name) = self.peek().clone() {
+                self.advance();
+                variables.push(name);
+            }
+            if variables.is_empty() {
+                return self.unexpected("variable or '*'");
+            }
+            selected = Some(variables);
+        }
+        self.accept_keyword("WHERE");
+        let elements = self.parse_group()?;
+        let mut limit: Option<usize> = None;
+        if self.accept_keyword("LIMIT") {
+            limit = Some(self.expect_integer()?);
+        }
+        if *self.peek() != Token::End {
+            return self.unexpected("end of input");
+        }
+        let query = query::Query::from_elements(&elements);
+        Ok(SelectQuery{
+            variables: selected.unwrap_or_else(|| query.variables().to_vec()),
+            limit,
+            query
+        })
+    }
+
+    fn parse_group(&mut self) -> Result<Vec<query::Element>, ParseError> {
+        self.expect_punctuation("{")?;
+        let mut elements: Vec<query::Element> = vec![];
+        loop {
+            if self.accept_punctuation("}") {
+                return Ok(elements);
+            } else if self.accept_keyword("OPTIONAL") {
+                elements.push(query::Element::Optional(self.parse_group()?));
+            } else if self.accept_keyword("FILTER") {
+                elements.push(match self.parse_unary()? {
+                    query::Expression::Not(operand) => match *operand {
+                        query::Expression::Exists(group) => query::Element::NotExists(group),
+                        operand => query::Element::Filter(query::Expression::Not(Box::new(operand)))
+                    },
+                    expression => query::Element::Filter(expression)
+                });
+            } else if *self.peek() == Token::End {
+                return self.unexpected("'}'");
+            } else {
+                let entity = self.parse_term()?;
+                let attribute = self.parse_term()?;
+                let value = self.parse_term()?;
+                elements.push(query::Element::Pattern([entity, attribute, value]));
+                self.accept_punctuation(".");
+            }
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<query::Term, ParseError> {
+        let namespace_identity = match self.peek().clone() {
+            Token::Variable(name) => {
+                self.advance();
+                return Ok(query::Term::Variable(name));
+            },
+            Token::Integer(namespace_identity) => namespace_identity,
+            Token::Name(name) => match self.prefixes.get(&name) {
+                Some(namespace_identity) if *self.peek_at(1) == Token::Punctuation(":") => *namespace_identity,
+                None if *self.peek_at(1) == Token::Punctuation(":") => { return self.error(format!("undefined prefix '{}'", name)); },
+                _ => { return self.unexpected("variable or symbol"); }
+            },
+            _ => { return self.unexpected("variable or symbol"); }
+        };
+        self.advance();
+        self.expect_punctuation(":")?;
+        let symbol_identity = self.expect_integer()?;
+        Ok(query::Term::Constant(symbol::Symbol(namespace_identity, symbol_identity)))
+    }
+
+    fn parse_expression(&mut self) -> Result<query::Expression, ParseError> {
+        let mut expression = self.parse_conjunction()?;
+        while self.accept_punctuation("||") {
+            expression = query::Expression::Or(Box::new(expression), Box::new(self.parse_conjunction()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_conjunction(&mut self) -> Result<query::Expression, ParseError> {
+        let mut expression = self.parse_unary()?;
+        while self.accept_punctuation("&&") {
+            expression = query::Expression::And(Box::new(expression), Box::new(self.parse_unary()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<query::Expression, ParseError> {
+        if self.accept_punctuation("!") {
+            return Ok(query::Expression::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.accept_punctuation("(") {
+            let expression = self.parse_expression()?;
+            self.expect_punctuation(")")?;
+            return Ok(expression);
+        }
+        if self.accept_keyword("NOT") {
+            self.expect_keyword("EXISTS")?;
+            return Ok(query::Expression::Not(Box::new(query::Expression::Exists(self.parse_group()?))));
+        }
+        if self.accept_keyword("EXISTS") {
+            return Ok(query::Expression::Exists(self.parse_group()?));
+        }
+        let left = self.parse_term()?;
+        if self.accept_punctuation("=") {
+            Ok(query::Expression::Equal(left, self.parse_term()?))
+        } else if self.accept_punctuation("!=") {
+            Ok(query::Expression::NotEqual(left, self.parse_term()?))
+        } else {
+            self.unexpected("'=' or '!='")
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<SelectQuery, ParseError> {
+    let mut parser = Parser{lexemes: tokenize(text)?, position: 0, prefixes: HashMap::new()};
+    parser.parse_query()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(symbol_identity: symbol::Identity) -> Option<symbol::Symbol> {
+        Some(symbol::Symbol(1, symbol_identity))
+    }
+
+    fn setup() {
+        graph::manifest_symbol(symbol::Symbol(0, 0));
+        graph::manifest_symbol(symbol::Symbol(0, 1));
+        for symbol_identity in [1, 2, 10, 11, 12, 13] {
+            graph::manifest_symbol(symbol::Symbol(1, symbol_identity));
+        }
+        for [entity, attribute, value] in [[10, 1, 11], [11, 1, 12], [12, 2, 13]] {
+            assert!(graph::set_triple([symbol::Symbol(1, entity), symbol::Symbol(1, attribute), symbol::Symbol(1, value)], true));
+        }
+        graph::set_ordered_results(true);
+    }
+
+    fn rows(text: &str) -> Vec<Vec<Option<symbol::Symbol>>> {
+        parse(text).unwrap().evaluate().rows
+    }
+
+    fn error(text: &str) -> ParseError {
+        parse(text).err().unwrap()
+    }
+
+    #[test]
+    fn prefixes_with_and_without_brackets() {
+        setup();
+        assert_eq!(rows("PREFIX ex: <1> PREFIX ns: 1 SELECT ?x WHERE { ?x ex:1 ns:11 }"), vec![vec![symbol(10)]]);
+    }
+
+    #[test]
+    fn select_star_and_limit() {
+        setup();
+        let bindings = parse("SELECT * { ?x 1:1 ?y }").unwrap().evaluate();
+        assert_eq!(bindings.variables, ["x", "y"]);
+        assert_eq!(bindings.rows, vec![vec![symbol(10), symbol(11)], vec![symbol(11), symbol(12)]]);
+        assert_eq!(rows("SELECT * { ?x 1:1 ?y } LIMIT 1"), vec![vec![symbol(10), symbol(11)]]);
+    }
+
+    #[test]
+    fn optional() {
+        setup();
+        assert_eq!(rows("SELECT ?x ?z { ?x 1:1 ?y . OPTIONAL { ?y 1:2 ?z } }"), vec![vec![symbol(10), None], vec![symbol(11), symbol(13)]]);
+    }
+
+    #[test]
+    fn filter_exists_and_not_exists() {
+        setup();
+        assert_eq!(rows("SELECT ?x { ?x 1:1 ?y FILTER EXISTS { ?y 1:1 ?w } }"), vec![vec![symbol(10)]]);
+        assert_eq!(rows("SELECT ?x { ?x 1:1 ?y FILTER NOT EXISTS { ?y 1:1 ?w } }"), vec![vec![symbol(11)]]);
+    }
+
+    #[test]
+    fn conjunction_binds_tighter_than_disjunction() {
+        setup();
+        assert_eq!(rows("SELECT ?x { ?x 1:1 ?y FILTER(?x = 1:10 || ?x = 1:11 && ?y = 1:13) }"), vec![vec![symbol(10)]]);
+        assert!(rows("SELECT ?x { ?x 1:1 ?y FILTER((?x = 1:10 || ?x = 1:11) && ?y = 1:13) }").is_empty());
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error("SELECT ?x {\n  ?x ex:1 ?y }"), ParseError{line: 2, column: 6, message: "undefined prefix 'ex'".to_string()});
+        assert_eq!(error("SELECT ?x { ?x 1:1 @ }"), ParseError{line: 1, column: 20, message: "unexpected character '@'".to_string()});
+        assert_eq!(error("SELECT ?x {\n?x 1:1 ?y"), ParseError{line: 2, column: 10, message: "expected '}', found end of input".to_string()});
+    }
+}
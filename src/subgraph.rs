@@ -0,0 +1,41 @@
+use crate::graph;
+use crate::query;
+
+pub fn find_matches(edges: &[query::Pattern], injective: bool) -> query::Bindings {
+    let mut query = query::Query::new(edges);
+    query.set_injective(injective);
+    let mut bindings = query.evaluate();
+    if graph::ordered_results() {
+        bindings.rows.sort_unstable();
+    }
+    bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbol;
+
+    fn variable(name: &str) -> query::Term {
+        query::Term::Variable(name.to_string())
+    }
+
+    #[test]
+    fn injectivity_prunes_revisited_symbols() {
+        graph::manifest_symbol(symbol::Symbol(0, 0));
+        graph::manifest_symbol(symbol::Symbol(0, 1));
+        let [a, b, link] = [graph::create_symbol(1), graph::create_symbol(1), graph::create_symbol(1)];
+        assert!(graph::set_triple([a, link, b], true));
+        assert!(graph::set_triple([b, link, a], true));
+        let edges = [
+            [variable("x"), query::Term::Constant(link), variable("y")],
+            [variable("y"), query::Term::Constant(link), variable("z")]
+        ];
+        let mut rows = find_matches(&edges, false).rows;
+        rows.sort_unstable();
+        assert_eq!(rows, vec![vec![Some(a), Some(b), Some(a)], vec![Some(b), Some(a), Some(b)]]);
+        assert!(find_matches(&edges, true).rows.is_empty());
+        let edges = [[variable("x"), query::Term::Constant(link), variable("y")]];
+        assert_eq!(find_matches(&edges, true).rows.len(), 2);
+    }
+}
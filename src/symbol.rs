@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 pub type Identity = usize;
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 pub struct Symbol(pub Identity, pub Identity);
 
 #[derive(Clone, Copy)]
@@ -32,6 +32,22 @@ impl VecIdentityPool {
         self.collection[0].begin
     }
 
+    pub fn upper_bound(&self) -> Identity {
+        self.collection[self.collection.len()-1].begin
+    }
+
+    pub fn next_allocated(&self, identity: Identity) -> Option<Identity> {
+        let range_index = self.collection.partition_point(|range| range.begin <= identity);
+        if range_index == 0 {
+            return Some(identity);
+        }
+        if range_index == self.collection.len() {
+            return None;
+        }
+        let range = &self.collection[range_index-1];
+        Some(if identity < range.begin+range.length { range.begin+range.length } else { identity })
+    }
+
     pub fn is_full(&mut self) -> bool {
         self.collection.len() == 1 && self.get() == 0
     }
@@ -45,7 +61,7 @@ impl VecIdentityPool {
             return false;
         }
         let is_not_last = range_index < self.collection.len();
-        let mut range = &mut self.collection[range_index-1];
+        let range = &mut self.collection[range_index-1];
         if is_not_last && identity >= range.begin+range.length {
             return false;
         }
@@ -147,6 +163,21 @@ impl BTreeIdentityPool {
         *self.collection.iter().next().unwrap().0
     }
 
+    pub fn upper_bound(&self) -> Identity {
+        *self.collection.iter().next_back().unwrap().0
+    }
+
+    pub fn next_allocated(&self, identity: Identity) -> Option<Identity> {
+        let (range_begin, range_length) = match self.collection.range(..=identity).next_back() {
+            Some(range) => range,
+            None => { return Some(identity); }
+        };
+        if *range_begin == self.upper_bound() {
+            return None;
+        }
+        Some(if identity < range_begin+range_length { range_begin+range_length } else { identity })
+    }
+
     pub fn is_full(&mut self) -> bool {
         self.collection.len() == 1 && self.get() == 0
     }
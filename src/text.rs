@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::symbol;
+use crate::graph;
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    let mut token = String::new();
+    for character in text.chars() {
+        if character.is_alphanumeric() {
+            token.extend(character.to_lowercase());
+        } else if !token.is_empty() {
+            tokens.push(std::mem::take(&mut token));
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+struct Document {
+    term_frequencies: HashMap<String, usize>,
+    token_count: usize
+}
+
+struct TextIndex {
+    postings: HashMap<String, HashMap<symbol::Identity, usize>>,
+    documents: HashMap<symbol::Identity, Document>,
+    stale: HashSet<symbol::Identity>
+}
+
+impl TextIndex {
+    fn remove(&mut self, symbol_identity: symbol::Identity) {
+        if let Some(document) = self.documents.remove(&symbol_identity) {
+            for term in document.term_frequencies.keys() {
+                let posting = self.postings.get_mut(term).unwrap();
+                posting.remove(&symbol_identity);
+                if posting.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, symbol_identity: symbol::Identity, text: &str) {
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return;
+        }
+        let mut term_frequencies: HashMap<String, usize> = HashMap::new();
+        for token in &tokens {
+            *term_frequencies.entry(token.clone()).or_insert(0) += 1;
+        }
+        for (term, frequency) in &term_frequencies {
+            self.postings.entry(term.clone()).or_default().insert(symbol_identity, *frequency);
+        }
+        self.documents.insert(symbol_identity, Document{term_frequencies, token_count: tokens.len()});
+    }
+}
+
+thread_local!(static TEXT_INDEX: RefCell<HashMap<symbol::Identity, TextIndex>> = RefCell::new(HashMap::new()));
+
+
+
+pub fn is_indexed(namespace_identity: symbol::Identity) -> bool {
+    TEXT_INDEX.with(|text_index_cell| text_index_cell.borrow().contains_key(&namespace_identity))
+}
+
+pub fn create_text_index(namespace_identity: symbol::Identity) -> bool {
+    if is_indexed(namespace_identity) {
+        return false;
+    }
+    let mut text_index = TextIndex{postings: HashMap::new(), documents: HashMap::new(), stale: HashSet::new()};
+    for (symbol, bytes) in graph::bytes_in_namespace(namespace_identity) {
+        text_index.insert(symbol.1, &String::from_utf8_lossy(&bytes));
+    }
+    TEXT_INDEX.with(|text_index_cell| {
+        text_index_cell.borrow_mut().insert(namespace_identity, text_index);
+    });
+    true
+}
+
+pub fn drop_text_index(namespace_identity: symbol::Identity) -> bool {
+    TEXT_INDEX.with(|text_index_cell| text_index_cell.borrow_mut().remove(&namespace_identity).is_some())
+}
+
+pub fn data_changed(symbol: symbol::Symbol) {
+    TEXT_INDEX.with(|text_index_cell| {
+        if let Some(text_index) = text_index_cell.borrow_mut().get_mut(&symbol.0) {
+            text_index.stale.insert(symbol.1);
+        }
+    })
+}
+
+fn refresh_stale(namespace_identity: symbol::Identity) {
+    let stale = TEXT_INDEX.with(|text_index_cell| {
+        text_index_cell.borrow_mut().get_mut(&namespace_identity).map_or(HashSet::new(), |text_index| std::mem::take(&mut text_index.stale))
+    });
+    let documents: Vec<(symbol::Identity, Option<Vec<u8>>)> = stale.into_iter().map(|symbol_identity| (symbol_identity, graph::read_bytes(symbol::Symbol(namespace_identity, symbol_identity)))).collect();
+    TEXT_INDEX.with(|text_index_cell| {
+        if let Some(text_index) = text_index_cell.borrow_mut().get_mut(&namespace_identity) {
+            for (symbol_identity, bytes) in documents {
+                text_index.remove(symbol_identity);
+                if let Some(bytes) = bytes {
+                    text_index.insert(symbol_identity, &String::from_utf8_lossy(&bytes));
+                }
+            }
+        }
+    })
+}
+
+pub fn search_text(namespace_identity: symbol::Identity, query: &str) -> Option<Vec<(symbol::Symbol, f64)>> {
+    refresh_stale(namespace_identity);
+    TEXT_INDEX.with(|text_index_cell| {
+        let text_index = text_index_cell.borrow();
+        let text_index = text_index.get(&namespace_identity)?;
+        let mut terms = tokenize(query);
+        terms.sort_unstable();
+        terms.dedup();
+        let document_count = text_index.documents.len() as f64;
+        let mut scores: HashMap<symbol::Identity, f64> = HashMap::new();
+        for term in &terms {
+            if let Some(posting) = text_index.postings.get(term) {
+                let inverse_document_frequency = ((document_count+1.0)/(posting.len() as f64+1.0)).ln()+1.0;
+                for (symbol_identity, frequency) in posting {
+                    let term_frequency = *frequency as f64/text_index.documents[symbol_identity].token_count as f64;
+                    *scores.entry(*symbol_identity).or_insert(0.0) += term_frequency*inverse_document_frequency;
+                }
+            }
+        }
+        let mut result: Vec<(symbol::Symbol, f64)> = scores.into_iter().map(|(symbol_identity, score)| (symbol::Symbol(namespace_identity, symbol_identity), score)).collect();
+        result.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        Some(result)
+    })
+}
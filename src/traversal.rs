@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use crate::symbol;
+use crate::graph;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Direction {
+    Forward, Backward
+}
+
+impl Direction {
+    fn triple_index(self) -> graph::TripleIndex {
+        match self {
+            Direction::Forward => graph::TripleIndex::EAV,
+            Direction::Backward => graph::TripleIndex::VAE
+        }
+    }
+}
+
+pub fn reachable(start: symbol::Symbol, attribute: symbol::Symbol, direction: Direction, max_depth: usize) -> Vec<(symbol::Symbol, usize)> {
+    let mut result: Vec<(symbol::Symbol, usize)> = vec![];
+    let mut visited: HashSet<symbol::Symbol> = HashSet::new();
+    let mut reached_start = false;
+    let mut queue: VecDeque<(symbol::Symbol, usize)> = VecDeque::new();
+    visited.insert(start);
+    queue.push_back((start, 0));
+    while let Some((alpha, distance)) = queue.pop_front() {
+        if distance >= max_depth {
+            continue;
+        }
+        for gamma in graph::query_gammas(direction.triple_index(), alpha, attribute) {
+            if gamma == start && !reached_start {
+                reached_start = true;
+                result.push((gamma, distance+1));
+            } else if visited.insert(gamma) {
+                result.push((gamma, distance+1));
+                queue.push_back((gamma, distance+1));
+            }
+        }
+    }
+    result
+}
+
+pub fn transitive_closure(start: symbol::Symbol, attribute: symbol::Symbol, direction: Direction) -> Vec<(symbol::Symbol, usize)> {
+    reachable(start, attribute, direction, usize::MAX)
+}
+
+
+pub struct PathFilter {
+    pub attributes: Option<HashSet<symbol::Symbol>>,
+    pub namespaces: Option<HashSet<symbol::Identity>>,
+    pub max_depth: usize
+}
+
+impl PathFilter {
+    pub fn new() -> Self {
+        Self{attributes: None, namespaces: None, max_depth: usize::MAX}
+    }
+}
+
+struct SearchFrontier {
+    parents: HashMap<symbol::Symbol, (Option<(symbol::Symbol, graph::Triple)>, usize)>,
+    frontier: Vec<symbol::Symbol>,
+    depth: usize
+}
+
+impl SearchFrontier {
+    fn new(start: symbol::Symbol) -> Self {
+        let mut parents = HashMap::new();
+        parents.insert(start, (None, 0));
+        Self{parents, frontier: vec![start], depth: 0}
+    }
+
+    fn path_to(&self, mut symbol: symbol::Symbol) -> Vec<graph::Triple> {
+        let mut result: Vec<graph::Triple> = vec![];
+        while let Some((parent, triple)) = self.parents[&symbol].0 {
+            result.push(triple);
+            symbol = parent;
+        }
+        result
+    }
+
+    fn expand(&mut self, other: &SearchFrontier, endpoints: [symbol::Symbol; 2], filter: &PathFilter) -> Option<symbol::Symbol> {
+        let mut meeting: Option<(symbol::Symbol, usize)> = None;
+        let mut next_frontier: Vec<symbol::Symbol> = vec![];
+        for symbol in std::mem::take(&mut self.frontier) {
+            for (neighbour, triple) in neighbours(symbol, filter) {
+                if self.parents.contains_key(&neighbour) {
+                    continue;
+                }
+                if !endpoints.contains(&neighbour) && filter.namespaces.as_ref().is_some_and(|namespaces| !namespaces.contains(&neighbour.0)) {
+                    continue;
+                }
+                self.parents.insert(neighbour, (Some((symbol, triple)), self.depth+1));
+                if let Some((_parent, other_depth)) = other.parents.get(&neighbour) {
+                    if meeting.is_none_or(|(_meeting, meeting_depth)| *other_depth < meeting_depth) {
+                        meeting = Some((neighbour, *other_depth));
+                    }
+                }
+                next_frontier.push(neighbour);
+            }
+        }
+        self.frontier = next_frontier;
+        self.depth += 1;
+        meeting.map(|(meeting, _depth)| meeting)
+    }
+}
+
+fn neighbours(symbol: symbol::Symbol, filter: &PathFilter) -> Vec<(symbol::Symbol, graph::Triple)> {
+    let mut result: Vec<(symbol::Symbol, graph::Triple)> = vec![];
+    let is_allowed = |triple: &graph::Triple| filter.attributes.as_ref().is_none_or(|attributes| attributes.contains(&triple[1]));
+    let outgoing = graph::query_mask([graph::QueryMask::Match, graph::QueryMask::Varying, graph::QueryMask::Varying]);
+    let incoming = graph::query_mask([graph::QueryMask::Varying, graph::QueryMask::Varying, graph::QueryMask::Match]);
+    graph::query_triples_with(outgoing, [symbol, symbol, symbol], |triples| {
+        result.extend(triples.filter(is_allowed).map(|triple| (triple[2], triple)));
+    });
+    graph::query_triples_with(incoming, [symbol, symbol, symbol], |triples| {
+        result.extend(triples.filter(is_allowed).map(|triple| (triple[0], triple)));
+    });
+    result
+}
+
+pub fn shortest_path(source: symbol::Symbol, target: symbol::Symbol, filter: &PathFilter) -> Option<Vec<graph::Triple>> {
+    if source == target {
+        return Some(vec![]);
+    }
+    let mut forward = SearchFrontier::new(source);
+    let mut backward = SearchFrontier::new(target);
+    while !forward.frontier.is_empty() && !backward.frontier.is_empty() && forward.depth+backward.depth < filter.max_depth {
+        let meeting = if forward.frontier.len() <= backward.frontier.len() {
+            forward.expand(&backward, [source, target], filter)
+        } else {
+            backward.expand(&forward, [source, target], filter)
+        };
+        if let Some(meeting) = meeting {
+            let mut result = forward.path_to(meeting);
+            result.reverse();
+            result.extend(backward.path_to(meeting));
+            return Some(result);
+        }
+    }
+    None
+}
@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::symbol;
+use crate::graph;
+use crate::query;
+
+struct View {
+    query: query::Query,
+    rows: HashSet<Vec<Option<symbol::Symbol>>>
+}
+
+impl View {
+    fn affected_rows(&self, triple: graph::Triple, overlay: &HashSet<graph::Triple>) -> Vec<Vec<Option<symbol::Symbol>>> {
+        let mut result: Vec<Vec<Option<symbol::Symbol>>> = vec![];
+        for pattern_index in 0..self.query.pattern_count() {
+            if let Some(bound) = self.query.unify(pattern_index, triple) {
+                result.extend(self.query.evaluate_bound(&bound, overlay).rows);
+            }
+        }
+        result
+    }
+}
+
+struct ViewIndex {
+    next_view: usize,
+    views: HashMap<usize, View>
+}
+
+thread_local!(static VIEW_INDEX: RefCell<ViewIndex> = RefCell::new(ViewIndex{next_view: 0, views: HashMap::new()}));
+
+
+
+pub fn register_view(patterns: &[query::Pattern]) -> usize {
+    let query = query::Query::new(patterns);
+    let rows = query.evaluate().rows.into_iter().collect();
+    VIEW_INDEX.with(|view_index_cell| {
+        let mut view_index = view_index_cell.borrow_mut();
+        let view = view_index.next_view;
+        view_index.next_view += 1;
+        view_index.views.insert(view, View{query, rows});
+        view
+    })
+}
+
+pub fn unregister_view(view: usize) -> bool {
+    VIEW_INDEX.with(|view_index_cell| view_index_cell.borrow_mut().views.remove(&view).is_some())
+}
+
+pub fn read_view(view: usize) -> Option<query::Bindings> {
+    VIEW_INDEX.with(|view_index_cell| {
+        let view_index = view_index_cell.borrow();
+        let view = view_index.views.get(&view)?;
+        let mut rows: Vec<Vec<Option<symbol::Symbol>>> = view.rows.iter().cloned().collect();
+        if graph::ordered_results() {
+            rows.sort_unstable();
+        }
+        Some(query::Bindings{variables: view.query.variables().to_vec(), rows})
+    })
+}
+
+pub fn count_view(view: usize) -> Option<usize> {
+    VIEW_INDEX.with(|view_index_cell| view_index_cell.borrow().views.get(&view).map(|view| view.rows.len()))
+}
+
+pub fn triple_changed(triple: graph::Triple, linked: bool) {
+    VIEW_INDEX.with(|view_index_cell| {
+        let mut view_index = view_index_cell.borrow_mut();
+        if view_index.views.is_empty() {
+            return;
+        }
+        let mut overlay: HashSet<graph::Triple> = HashSet::new();
+        if !linked {
+            overlay.insert(triple);
+        }
+        for view in view_index.views.values_mut() {
+            let affected_rows = view.affected_rows(triple, &overlay);
+            for row in affected_rows {
+                if linked {
+                    view.rows.insert(row);
+                } else {
+                    view.rows.remove(&row);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history;
+
+    fn variable(name: &str) -> query::Term {
+        query::Term::Variable(name.to_string())
+    }
+
+    fn assert_views_fresh(views: &[(usize, Vec<query::Pattern>)]) {
+        for (view, patterns) in views {
+            let mut expected = query::Query::new(patterns).evaluate().rows;
+            expected.sort_unstable();
+            let mut rows = read_view(*view).unwrap().rows;
+            rows.sort_unstable();
+            assert_eq!(rows, expected);
+            assert_eq!(count_view(*view), Some(expected.len()));
+        }
+    }
+
+    #[test]
+    fn views_follow_links_and_history() {
+        graph::manifest_symbol(symbol::Symbol(0, 0));
+        graph::manifest_symbol(symbol::Symbol(0, 1));
+        history::enable_history(16);
+        let [a, b, c, link] = [graph::create_symbol(1), graph::create_symbol(1), graph::create_symbol(1), graph::create_symbol(1)];
+        history::commit_step();
+        let edge = |entity: &str, value: &str| [variable(entity), query::Term::Constant(link), variable(value)];
+        let views: Vec<(usize, Vec<query::Pattern>)> = vec![
+            vec![edge("x", "y")],
+            vec![edge("x", "y"), edge("y", "z")]
+        ].into_iter().map(|patterns| (register_view(&patterns), patterns)).collect();
+        assert_views_fresh(&views);
+        let changes = [([a, link, b], true), ([b, link, c], true), ([a, link, a], true), ([c, link, a], true), ([a, link, b], false), ([a, link, a], false)];
+        for (triple, linked) in changes {
+            assert!(graph::set_triple(triple, linked));
+            history::commit_step();
+            assert_views_fresh(&views);
+        }
+        assert_eq!(count_view(views[1].0), Some(1));
+        for _step in 0..3 {
+            assert!(history::undo());
+            assert_views_fresh(&views);
+        }
+        assert_eq!(count_view(views[1].0), Some(3));
+        for _step in 0..2 {
+            assert!(history::redo());
+            assert_views_fresh(&views);
+        }
+    }
+}